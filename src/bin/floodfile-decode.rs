@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use floodfile::network::capture::read_frames;
+use floodfile::network::frame::Frame;
+use floodfile::network::payload::Payload;
+use floodfile::network::reassembly::{Progress, Reassembler};
+
+const USAGE: &str = "usage: floodfile-decode <capture.pcap[ng]> [--export <dir>]";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn describe(payload: &Payload) -> String {
+    match payload {
+        Payload::File(hash, data) => format!("file {0} ({1} bytes)", hex(hash), data.len()),
+        Payload::Advertise(path) => format!("advertise {0:?}", path),
+        Payload::DownloadRequest(hash) => format!("download-request {0}", hex(hash)),
+        Payload::Discover => String::from("discover"),
+        Payload::CatalogRequest(target) => format!("catalog-request for {0}", target),
+        Payload::Catalog(page, pages, entries) => {
+            format!(
+                "catalog page {0}/{1} ({2} entries)",
+                page + 1,
+                pages,
+                entries.len()
+            )
+        }
+        Payload::Unshare(hash) => format!("unshare {0}", hex(hash)),
+        Payload::Carousel(path) => format!("carousel {0:?}", path),
+        Payload::Push(name, data) => format!("push {0:?} ({1} bytes)", name, data.len()),
+        Payload::Snippet(text) => format!("snippet {0:?}", text),
+        Payload::StreamOffer(id) => format!("stream-offer {0}", hex(id)),
+        Payload::StreamRequest(id, seq) => format!("stream-request {0} from #{1}", hex(id), seq),
+        Payload::StreamData(id, seq, data) => {
+            format!("stream-data {0} #{1} ({2} bytes)", hex(id), seq, data.len())
+        }
+        Payload::StreamEnd(id, segments) => {
+            format!("stream-end {0} after {1} segments", hex(id), segments)
+        }
+        Payload::StreamRefused(id, receiver) => {
+            format!("stream-refused {0} for {1}", hex(id), receiver)
+        }
+        Payload::LossReport(key, received, expected) => {
+            format!(
+                "loss-report {0} ({1}/{2} chunks)",
+                hex(key),
+                received,
+                expected
+            )
+        }
+    }
+}
+
+fn export(dir: &Path, frame: &Frame, payload: &Payload, raw: &[u8]) -> std::io::Result<PathBuf> {
+    // files are written decompressed, everything else as its serialized bytes
+    let (name, data) = match payload {
+        Payload::File(hash, data) => (format!("{0}.bin", hex(hash)), &data[..]),
+        _ => {
+            let source = frame.source.to_string().replace(':', "");
+            let kind = Payload::kind(frame.opcode);
+            (format!("{0}-{1}.{2}", source, hex(&frame.key), kind), raw)
+        }
+    };
+
+    let path = dir.join(name);
+    fs::write(&path, data)?;
+    Ok(path)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (capture, export_dir) = match &args[..] {
+        [capture] => (capture, None),
+        [capture, flag, dir] if flag == "--export" => (capture, Some(PathBuf::from(dir))),
+        _ => {
+            eprintln!("{0}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let frames = match read_frames(Path::new(capture)) {
+        Ok(frames) => frames,
+        Err(e) => {
+            eprintln!("error: {0}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut reassembler = Reassembler::default();
+    for (index, buffer) in frames.iter().enumerate() {
+        let (frame, _) = match Frame::decode(buffer) {
+            Some(frame) => frame,
+            None => continue,
+        };
+
+        let vlan = match frame.vlan {
+            Some(vlan) => format!(" vlan={0}:{1}", vlan.id, vlan.priority),
+            None => String::new(),
+        };
+        let relayed = match frame.relayed_by {
+            Some(relay) => format!(" hops={0} via={1}", frame.hops, relay),
+            None => String::new(),
+        };
+        print!(
+            "#{0:<6} {1} op={2}({3}) key={4} nonce={5} chunk={6}/{7} len={8}{9}{10} ",
+            index + 1,
+            frame.source,
+            frame.opcode,
+            Payload::kind(frame.opcode),
+            hex(&frame.key),
+            hex(&frame.nonce),
+            frame.offset + 1,
+            frame.total,
+            frame.chunk.len(),
+            vlan,
+            relayed,
+        );
+
+        match reassembler.push(frame.clone()) {
+            Ok(Progress::Partial { received, total }) => {
+                println!("partial {0}/{1}", received, total)
+            }
+            Ok(Progress::Complete { opcode, data }) => match Payload::deserialize(opcode, &data) {
+                Some(payload) => {
+                    println!("complete: {0}", describe(&payload));
+                    if let Some(dir) = &export_dir {
+                        match export(dir, &frame, &payload, &data) {
+                            Ok(path) => println!("        exported {0}", path.display()),
+                            Err(e) => eprintln!("        unable to export: {0}", e),
+                        }
+                    }
+                }
+                None => println!("complete: undecodable {0} bytes", data.len()),
+            },
+            Err(e) => println!("rejected: {0}", e),
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,509 @@
+use clap::{Args, Parser, Subcommand};
+use crossbeam::channel::RecvTimeoutError;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use crate::errors::FloodFileError;
+use crate::network::utils::usable_interfaces;
+use crate::network::{stream, Channel, StreamId};
+use crate::session::{Event, Session, ShareId, ShareOptions};
+
+// the same code clap exits with for usage errors
+pub const EXIT_USAGE: u8 = 2;
+pub const EXIT_NOT_FOUND: u8 = 3;
+pub const EXIT_TIMEOUT: u8 = 4;
+
+#[derive(Parser)]
+#[command(name = "floodfile", version, about = "Share files over ARP")]
+pub struct Cli {
+    // no subcommand starts the terminal ui
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Args)]
+pub struct Common {
+    /// Interface to use, the busiest usable one by default
+    #[arg(long)]
+    iface: Option<String>,
+
+    /// Print one JSON object per line instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+pub enum CliCommand {
+    /// Share a file and serve it until the share stops
+    Share {
+        path: PathBuf,
+
+        /// Stop sharing after this many minutes
+        #[arg(long)]
+        minutes: Option<u64>,
+
+        /// Stop sharing after this many downloads
+        #[arg(long)]
+        max_downloads: Option<u32>,
+
+        #[command(flatten)]
+        common: Common,
+    },
+
+    /// List shares advertised on the network
+    List {
+        /// Seconds to listen for advertisements
+        #[arg(long, default_value_t = 3)]
+        wait: u64,
+
+        #[command(flatten)]
+        common: Common,
+    },
+
+    /// Download a share by its id, as printed by list
+    Get {
+        id: String,
+
+        /// Directory to save into
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+
+        /// Seconds to wait for the share to appear and finish downloading
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+
+        #[command(flatten)]
+        common: Common,
+    },
+
+    /// Print network activity as it happens
+    Listen {
+        #[command(flatten)]
+        common: Common,
+    },
+
+    /// Stream a file or standard input, for as long as it takes to read
+    Send {
+        /// File to stream, - for standard input
+        input: String,
+
+        /// Seconds to wait for a receiver
+        #[arg(long, default_value_t = 60)]
+        wait: u64,
+
+        #[command(flatten)]
+        common: Common,
+    },
+
+    /// Write a stream to standard output, by the id send printed
+    Recv {
+        id: String,
+
+        /// Seconds to wait without any progress before giving up
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+
+        #[command(flatten)]
+        common: Common,
+    },
+}
+
+pub fn run(command: CliCommand) -> ExitCode {
+    match command {
+        CliCommand::Send {
+            input,
+            wait,
+            common,
+        } => {
+            let input: Box<dyn Read + Send> = match input.as_str() {
+                "-" => Box::new(io::stdin()),
+                path => match File::open(path) {
+                    Ok(file) => Box::new(file),
+                    Err(_) => return fail(&common, &FloodFileError::FailedToReadFile.to_string()),
+                },
+            };
+            with_channel(&common, |channel| send(channel, &common, input, wait))
+        }
+        CliCommand::Recv {
+            id,
+            timeout,
+            common,
+        } => {
+            let id = match parse_id(&id) {
+                Some(id) => id,
+                None => return usage(&common, "stream ids are 32 hex digits"),
+            };
+            with_channel(&common, |channel| recv(channel, &common, id, timeout))
+        }
+        command => {
+            let common = command.common();
+            let session = match &common.iface {
+                Some(iface) => Session::open(iface),
+                None => Session::open_default(),
+            };
+
+            let session = match session {
+                Ok(session) => session,
+                Err(e) => return fail(common, &e.to_string()),
+            };
+
+            // withdraws our shares before exiting
+            let code = run_on(&session, command);
+            session.shutdown().ok();
+            code
+        }
+    }
+}
+
+// runs a command on a session that's already open. streams don't use one.
+pub fn run_on(session: &Session, command: CliCommand) -> ExitCode {
+    match command {
+        CliCommand::Share {
+            path,
+            minutes,
+            max_downloads,
+            common,
+        } => {
+            let stop_after = match minutes.map(|m| m.checked_mul(60)) {
+                Some(None) => return usage(&common, "--minutes is too large"),
+                seconds => seconds.flatten().map(Duration::from_secs),
+            };
+            let options = ShareOptions {
+                stop_after,
+                max_downloads,
+                ..Default::default()
+            };
+            share(session, &common, path, options)
+        }
+        CliCommand::List { wait, common } => list(session, &common, wait),
+        CliCommand::Get {
+            id,
+            output,
+            timeout,
+            common,
+        } => match parse_id(&id) {
+            Some(id) => get(session, &common, id, output, timeout),
+            None => usage(&common, "share ids are 32 hex digits"),
+        },
+        CliCommand::Listen { common } => listen(session, &common),
+        CliCommand::Send { common, .. } | CliCommand::Recv { common, .. } => {
+            usage(&common, "streams run without a session")
+        }
+    }
+}
+
+impl CliCommand {
+    fn common(&self) -> &Common {
+        match self {
+            CliCommand::Share { common, .. }
+            | CliCommand::List { common, .. }
+            | CliCommand::Get { common, .. }
+            | CliCommand::Listen { common }
+            | CliCommand::Send { common, .. }
+            | CliCommand::Recv { common, .. } => common,
+        }
+    }
+}
+
+// streams skip the session, they don't need shares or downloads
+fn with_channel(common: &Common, command: impl FnOnce(&mut Channel) -> ExitCode) -> ExitCode {
+    let interface = usable_interfaces()
+        .into_iter()
+        .find(|i| common.iface.as_ref().is_none_or(|name| i.name == *name));
+
+    let channel = match interface {
+        Some(interface) => Channel::new(interface),
+        None => Err(FloodFileError::InterfaceNotFound),
+    };
+
+    match channel {
+        Ok(mut channel) => command(&mut channel),
+        Err(e) => fail(common, &e.to_string()),
+    }
+}
+
+fn share(session: &Session, common: &Common, path: PathBuf, options: ShareOptions) -> ExitCode {
+    if let Err(e) = session.share_with(&path, options) {
+        return fail(common, &e.to_string());
+    }
+    unshare_on_interrupt(session, &path);
+
+    for event in session.events() {
+        match event {
+            Event::ShareStopped(_) => {
+                report(common, &event);
+                return ExitCode::SUCCESS;
+            }
+            event => report(common, &event),
+        }
+    }
+
+    fail(common, &FloodFileError::SessionClosed.to_string())
+}
+
+// ctrl-c stops the share the way running out does, so peers hear it's gone. a
+// second one quits straight away.
+#[cfg(unix)]
+fn unshare_on_interrupt(session: &Session, path: &Path) {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+        Ok(signals) => signals,
+        Err(_) => return,
+    };
+
+    let (session, path) = (session.clone(), path.to_path_buf());
+    std::thread::spawn(move || {
+        let mut signals = signals.forever();
+        if signals.next().is_some() {
+            session.unshare(&path).ok();
+        }
+        if let Some(signal) = signals.next() {
+            std::process::exit(128 + signal);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn unshare_on_interrupt(_: &Session, _: &Path) {}
+
+fn list(session: &Session, common: &Common, wait: u64) -> ExitCode {
+    let deadline = match Instant::now().checked_add(Duration::from_secs(wait)) {
+        Some(deadline) => deadline,
+        None => return usage(common, "--wait is too large"),
+    };
+    loop {
+        match session.events().recv_deadline(deadline) {
+            Ok(event @ Event::ShareDiscovered(_)) => report(common, &event),
+            Ok(_) => (),
+            Err(RecvTimeoutError::Timeout) => return ExitCode::SUCCESS,
+            Err(RecvTimeoutError::Disconnected) => {
+                return fail(common, &FloodFileError::SessionClosed.to_string())
+            }
+        }
+    }
+}
+
+fn get(session: &Session, common: &Common, id: ShareId, output: PathBuf, timeout: u64) -> ExitCode {
+    if !output.is_dir() {
+        return fail(common, "output is not a directory");
+    }
+
+    if let Err(e) = session.set_download_dir(&output) {
+        return fail(common, &e.to_string());
+    }
+
+    // the share may take an advertisement round to show up
+    let deadline = match Instant::now().checked_add(Duration::from_secs(timeout)) {
+        Some(deadline) => deadline,
+        None => return usage(common, "--timeout is too large"),
+    };
+    let known = session.remote_shares().unwrap_or_default();
+    if !known.iter().any(|s| s.id == id) {
+        loop {
+            match session.events().recv_deadline(deadline) {
+                Ok(Event::ShareDiscovered(share)) if share.id == id => break,
+                Ok(_) => (),
+                Err(RecvTimeoutError::Timeout) => {
+                    fail(common, "share not found");
+                    return ExitCode::from(EXIT_NOT_FOUND);
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return fail(common, &FloodFileError::SessionClosed.to_string())
+                }
+            }
+        }
+    }
+
+    let download = match session.download(id) {
+        Ok(download) => download,
+        Err(e) => return fail(common, &e.to_string()),
+    };
+
+    loop {
+        match session.events().recv_deadline(deadline) {
+            Ok(event @ Event::DownloadComplete { id, .. }) if id == download.id() => {
+                report(common, &event);
+                return ExitCode::SUCCESS;
+            }
+            Ok(Event::DownloadFailed { id, error }) if id == download.id() => {
+                return fail(common, &error)
+            }
+            Ok(event @ Event::DownloadProgress { id, .. }) if id == download.id() => {
+                report(common, &event)
+            }
+            Ok(_) => (),
+            Err(RecvTimeoutError::Timeout) => {
+                download.cancel().ok();
+                fail(common, "download timed out");
+                return ExitCode::from(EXIT_TIMEOUT);
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return fail(common, &FloodFileError::SessionClosed.to_string())
+            }
+        }
+    }
+}
+
+fn listen(session: &Session, common: &Common) -> ExitCode {
+    for event in session.events() {
+        report(common, &event);
+    }
+
+    fail(common, &FloodFileError::SessionClosed.to_string())
+}
+
+fn send(
+    channel: &mut Channel,
+    common: &Common,
+    input: Box<dyn Read + Send>,
+    wait: u64,
+) -> ExitCode {
+    let id: StreamId = rand::random();
+    match common.json {
+        true => println!("{0}", json!({"event": "stream", "id": hex(&id)})),
+        false => eprintln!("streaming as {0}", hex(&id)),
+    }
+
+    match stream::send(channel, input, id, Duration::from_secs(wait)) {
+        Ok(segments) => {
+            match common.json {
+                true => println!("{0}", json!({"event": "stream-sent", "segments": segments})),
+                false => eprintln!("sent {0} segments", segments),
+            }
+            ExitCode::SUCCESS
+        }
+        Err(FloodFileError::StreamTimedOut) => {
+            fail(common, &FloodFileError::StreamTimedOut.to_string());
+            ExitCode::from(EXIT_TIMEOUT)
+        }
+        Err(e) => fail(common, &e.to_string()),
+    }
+}
+
+fn recv(channel: &mut Channel, common: &Common, id: StreamId, timeout: u64) -> ExitCode {
+    // standard output carries the stream, everything else goes to standard error
+    let result = stream::receive(
+        channel,
+        io::stdout().lock(),
+        id,
+        Duration::from_secs(timeout),
+    );
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(FloodFileError::StreamTimedOut) => {
+            fail(common, &FloodFileError::StreamTimedOut.to_string());
+            ExitCode::from(EXIT_TIMEOUT)
+        }
+        Err(e) => fail(common, &e.to_string()),
+    }
+}
+
+fn report(common: &Common, event: &Event) {
+    let (value, text) = match describe(event) {
+        Some(described) => described,
+        None => return,
+    };
+
+    // errors go to standard error in either mode, like our own failures
+    match (common.json, matches!(event, Event::Error(_))) {
+        (true, false) => println!("{0}", value),
+        (true, true) => eprintln!("{0}", value),
+        (false, false) => println!("{0}", text),
+        (false, true) => eprintln!("error: {0}", text),
+    }
+}
+
+fn fail(common: &Common, error: &str) -> ExitCode {
+    match common.json {
+        true => eprintln!("{0}", json!({"event": "error", "error": error})),
+        false => eprintln!("error: {0}", error),
+    }
+    ExitCode::FAILURE
+}
+
+fn usage(common: &Common, error: &str) -> ExitCode {
+    fail(common, error);
+    ExitCode::from(EXIT_USAGE)
+}
+
+fn describe(event: &Event) -> Option<(Value, String)> {
+    let described = match event {
+        Event::Sharing(path) => (
+            json!({"event": "sharing", "path": path}),
+            format!("sharing {0}", path),
+        ),
+        Event::ShareStopped(path) => (
+            json!({"event": "share-stopped", "path": path}),
+            format!("stopped sharing {0}", path),
+        ),
+        Event::ShareDiscovered(share) => (
+            json!({
+                "event": "share",
+                "id": hex(&share.id),
+                "name": share.name,
+                "peer": share.peer.to_string(),
+                "interface": share.interface,
+                "carousel": share.carousel,
+            }),
+            format!("{0}  {1}  {2}", hex(&share.id), share.peer, share.name),
+        ),
+        Event::ShareWithdrawn(id) => (
+            json!({"event": "share-withdrawn", "id": hex(id)}),
+            format!("withdrawn {0}", hex(id)),
+        ),
+        Event::DownloadProgress {
+            received, total, ..
+        } => (
+            json!({"event": "progress", "received": received, "total": total}),
+            format!("received {0}/{1} chunks", received, total),
+        ),
+        Event::DownloadComplete { path, .. } => (
+            json!({"event": "saved", "path": path}),
+            format!("saved {0}", path.display()),
+        ),
+        Event::PushSaved(path) => (
+            json!({"event": "push-saved", "path": path}),
+            format!("pushed to us: {0}", path.display()),
+        ),
+        Event::PushOffered {
+            peer, name, size, ..
+        } => (
+            json!({"event": "push-offered", "peer": peer.to_string(), "name": name, "size": size}),
+            format!("{0} offered {1} ({2} bytes)", peer, name, size),
+        ),
+        Event::SnippetReceived(snippet) => (
+            json!({"event": "snippet", "peer": snippet.peer.to_string(), "text": snippet.text}),
+            format!("{0}: {1}", snippet.peer, snippet.text),
+        ),
+        Event::Notice(message) => (
+            json!({"event": "notice", "message": message}),
+            message.clone(),
+        ),
+        Event::Error(message) => (json!({"event": "error", "error": message}), message.clone()),
+        _ => return None,
+    };
+
+    Some(described)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn parse_id(id: &str) -> Option<ShareId> {
+    if id.len() != 32 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut share = [0u8; 16];
+    for (i, byte) in share.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&id[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(share)
+}
@@ -1,99 +1,211 @@
 use crossbeam::channel::unbounded;
+use cursive::traits::Nameable;
 use cursive::views::{Dialog, LinearLayout, TextView};
-use std::fs;
-use std::thread;
-use ui::start_ui;
+use std::time::{Duration, Instant};
+use ui::{refresh_interfaces, refresh_my_shares, start_ui};
 
 use crate::display::alert::alert_user;
-use crate::display::network_thread::network_thread;
+use crate::network::utils::usable_interfaces;
+use crate::session::{Event, Session, ShareId};
 
+pub mod advertise;
 pub mod alert;
-pub mod config;
-pub mod network_thread;
+pub mod browse;
+pub mod carousel;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod filters;
 pub mod path;
+pub mod push;
+pub mod record;
+pub mod relay;
+pub mod schedule;
+pub mod snippets;
 pub mod ui;
+pub mod vlan;
 
 pub enum DisplayCommand {
     AdvertiseFile(String),
-    NewFile(String),
-    ChangeInterface(String),
-    AlertUser(String),
-}
-
-pub enum NetworkCommand {
-    UpdateLocalPath(String),
-    AdvertiseFile(String),
-    RequestFile(String),
-    ChangeInterface(String),
+    ToggleInterface(String),
 }
 
 pub fn start() {
     let (display_tx, display_rx) = unbounded::<DisplayCommand>();
-    let (network_tx, network_rx) = unbounded::<NetworkCommand>();
-
-    thread::spawn({
-        let display_tx = display_tx.clone();
-        move || network_thread(display_tx, network_rx)
-    });
+    let session = Session::open_default().expect("Error: failed to create network channel.");
 
     let mut siv = cursive::default();
 
     // load theme or exit with user-friendly error
     siv.load_toml(include_str!("../assets/theme.toml"))
         .expect("Error: Unable to load application theme. Please restart the application.");
+    filters::load_filters(&mut siv, &session);
 
     siv.menubar().add_leaf("quit", |siv| siv.quit());
     siv.menubar().add_leaf("storage-path", {
-        let tx = network_tx.clone();
-        move |siv| path::change_path(siv, &tx)
+        let session = session.clone();
+        move |siv| path::change_path(siv, &session)
+    });
+    siv.menubar().add_leaf("snippets", {
+        let session = session.clone();
+        move |siv| snippets::show_snippets(siv, &session)
+    });
+    siv.menubar().add_leaf("pushes", {
+        let session = session.clone();
+        move |siv| push::show_pushes(siv, &session)
+    });
+    siv.menubar().add_leaf("schedule", {
+        let session = session.clone();
+        move |siv| schedule::schedule_share(siv, &session)
+    });
+    siv.menubar().add_leaf("carousel", {
+        let session = session.clone();
+        move |siv| carousel::start_carousel(siv, &session)
+    });
+    siv.menubar().add_leaf("browse", {
+        let session = session.clone();
+        move |siv| browse::choose_peer(siv, &session)
+    });
+    siv.menubar().add_leaf("filters", {
+        let session = session.clone();
+        move |siv| filters::show_filters(siv, &session)
+    });
+    siv.menubar().add_leaf("vlan", {
+        let session = session.clone();
+        move |siv| vlan::change_vlan(siv, &session)
+    });
+    siv.menubar().add_leaf("relay", {
+        let session = session.clone();
+        move |siv| relay::change_relay(siv, &session)
+    });
+    siv.menubar().add_leaf("record", {
+        let session = session.clone();
+        move |siv| record::change_recording(siv, &session)
+    });
+    siv.menubar().add_leaf("advertise-interval", {
+        let session = session.clone();
+        move |siv| advertise::change_interval(siv, &session)
     });
 
     siv.set_autohide_menu(false);
     start_ui(&mut siv, display_tx.clone());
 
+    let active = session.active_interfaces().unwrap_or_default();
+    refresh_interfaces(&mut siv, usable_interfaces(), &active);
+
     let mut siv = siv.runner();
+    let mut shares_refreshed = Instant::now();
     while siv.is_running() {
+        // countdowns tick once a second
+        if shares_refreshed.elapsed() >= Duration::from_secs(1) {
+            let shares = session.local_shares().unwrap_or_default();
+            refresh_my_shares(&mut siv, &shares);
+            shares_refreshed = Instant::now();
+        }
+
         siv.refresh();
         while let Ok(command) = display_rx.try_recv() {
             match command {
                 DisplayCommand::AdvertiseFile(file) => {
-                    if !fs::exists(&file).unwrap() {
-                        alert_user(&mut siv, String::from("file not found"));
-                        continue;
+                    // directories and unreadable paths are turned away here
+                    if let Err(e) = session.share(&file) {
+                        alert_user(&mut siv, e.to_string());
                     }
-
-                    // send file to network thread
-                    network_tx
-                        .send(NetworkCommand::AdvertiseFile(file.clone()))
-                        .expect("Error: network thread has died.");
                 }
-                DisplayCommand::NewFile(file) => {
-                    let n_tx = network_tx.clone();
+                DisplayCommand::ToggleInterface(interface) => {
+                    let active = session.active_interfaces().unwrap_or_default();
+                    match active.contains(&interface) {
+                        true => session.remove_interface(&interface),
+                        false => session.add_interface(&interface),
+                    }
+                    .expect("Error: unable to change interface");
+                }
+            }
+        }
+
+        while let Ok(event) = session.events().try_recv() {
+            match event {
+                Event::ShareDiscovered(share) => {
+                    let session = session.clone();
                     siv.call_on_name("file_list", move |file_list: &mut LinearLayout| {
+                        let name = share_view_name(&share.id);
+                        let label = match share.carousel {
+                            true => format!("{0} ({1}, carousel)", share.name, share.interface),
+                            false => format!("{0} ({1})", share.name, share.interface),
+                        };
                         let available =
-                            Dialog::around(TextView::new(&file)).button("download", move |_s| {
-                                n_tx.send(NetworkCommand::RequestFile(file.clone()))
+                            Dialog::around(TextView::new(label)).button("download", move |_s| {
+                                session
+                                    .download(share.id)
                                     .expect("Error: unable to request file.");
                             });
 
-                        file_list.add_child(available);
+                        file_list.add_child(available.with_name(name));
                     });
                 }
-                DisplayCommand::ChangeInterface(interface) => {
-                    network_tx
-                        .send(NetworkCommand::ChangeInterface(interface.clone()))
-                        .expect("Error: unable to change interface");
-
+                Event::ShareWithdrawn(id) => {
+                    siv.call_on_name("file_list", |file_list: &mut LinearLayout| {
+                        if let Some(idx) = file_list.find_child_from_name(&share_view_name(&id)) {
+                            file_list.remove_child(idx);
+                        }
+                    });
+                }
+                Event::Sharing(_) => alert_user(&mut siv, String::from("sharing!")),
+                Event::PushOffered {
+                    id,
+                    peer,
+                    name,
+                    size,
+                } => push::offer_push(&mut siv, &session, id, peer, &name, size),
+                Event::SnippetReceived(snippet) => {
+                    alert_user(&mut siv, format!("snippet from {0}", snippet.peer))
+                }
+                Event::PushSaved(path) => {
+                    alert_user(&mut siv, format!("pushed to you: {0}", path.display()))
+                }
+                Event::ShareStopped(file) => {
+                    alert_user(&mut siv, format!("stopped sharing: {0}", file))
+                }
+                Event::CarouselEnded(file) => {
+                    alert_user(&mut siv, format!("carousel ended: {0}", file))
+                }
+                Event::DownloadComplete { path, .. } => {
+                    alert_user(&mut siv, format!("saved: {0}", path.display()))
+                }
+                Event::DownloadFailed { error, .. } => alert_user(&mut siv, error),
+                Event::Catalog { peer, entries } => {
+                    browse::show_catalog(&mut siv, peer, entries, &session);
+                }
+                Event::Interfaces(interfaces) => {
+                    let active = session.active_interfaces().unwrap_or_default();
+                    refresh_interfaces(&mut siv, interfaces, &active);
+                }
+                Event::Paused(interface) => alert_user(
+                    &mut siv,
+                    format!("{0} is down, transfers paused", interface),
+                ),
+                Event::Resumed(interface) => {
+                    alert_user(&mut siv, format!("{0} is back, resuming", interface))
+                }
+                Event::FailedOver(interface) => {
                     siv.call_on_name("file_list", |file_list: &mut LinearLayout| {
                         file_list.clear();
                     });
+                    alert_user(&mut siv, format!("link lost, switched to {0}", interface));
                 }
-                DisplayCommand::AlertUser(message) => {
+                Event::Notice(message) | Event::Error(message) => {
                     alert_user(&mut siv, message);
                 }
+                Event::DownloadProgress { .. } => (),
             }
         }
 
         siv.step();
     }
+
+    // tell peers our shares are gone before the interface closes
+    session.shutdown().ok();
+}
+
+fn share_view_name(id: &ShareId) -> String {
+    id.iter().map(|b| format!("{:02x}", b)).collect()
 }
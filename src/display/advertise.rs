@@ -0,0 +1,31 @@
+use cursive::{
+    views::{Dialog, EditView},
+    Cursive,
+};
+use std::time::Duration;
+
+use crate::session::Session;
+
+pub fn change_interval(siv: &mut Cursive, session: &Session) {
+    siv.add_layer(
+        Dialog::around(EditView::new().on_submit({
+            let session = session.clone();
+            move |siv, secs: &str| {
+                siv.pop_layer();
+
+                let secs = match secs.trim().parse::<u64>() {
+                    Ok(secs) if secs > 0 => secs,
+                    _ => {
+                        siv.add_layer(Dialog::info("invalid interval"));
+                        return;
+                    }
+                };
+
+                session
+                    .set_advertise_interval(Duration::from_secs(secs))
+                    .expect("Error: unable to update advertise interval.");
+            }
+        }))
+        .title("Enter seconds between advertisements"),
+    );
+}
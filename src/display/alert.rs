@@ -9,7 +9,6 @@ pub fn alert_user(siv: &mut CursiveRunner<&mut Cursive>, message: String) {
             .content(TextView::new(message))
             .button("ok", |s| {
                 s.pop_layer();
-                return;
             }),
     );
 }
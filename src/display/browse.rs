@@ -0,0 +1,83 @@
+use cursive::{
+    traits::{Nameable, Resizable, Scrollable},
+    views::{Dialog, SelectView},
+    Cursive,
+};
+use pnet::util::MacAddr;
+
+use crate::network::payload::CatalogEntry;
+use crate::network::utils::compute_filehash;
+use crate::session::Session;
+
+pub fn choose_peer(siv: &mut Cursive, session: &Session) {
+    let peers = match session.peers() {
+        Ok(peers) => peers,
+        Err(e) => {
+            siv.add_layer(Dialog::info(e.to_string()));
+            return;
+        }
+    };
+
+    if peers.is_empty() {
+        siv.add_layer(Dialog::info("no peers seen yet"));
+        return;
+    }
+
+    siv.add_layer(
+        Dialog::around(
+            SelectView::new()
+                .with_all(peers.into_iter().map(|p| (p.to_string(), p)))
+                .on_submit({
+                    let session = session.clone();
+                    move |siv, peer: &MacAddr| {
+                        session
+                            .request_catalog(*peer)
+                            .expect("Error: unable to request catalog.");
+
+                        siv.pop_layer();
+                    }
+                })
+                .scrollable(),
+        )
+        .title("browse peer")
+        .dismiss_button("cancel"),
+    );
+}
+
+pub fn show_catalog(
+    siv: &mut Cursive,
+    peer: MacAddr,
+    entries: Vec<CatalogEntry>,
+    session: &Session,
+) {
+    let mut catalog = SelectView::new().on_submit({
+        let session = session.clone();
+        move |_, entry: &CatalogEntry| {
+            if let Ok(hash) = compute_filehash(&entry.name) {
+                session
+                    .download(hash)
+                    .expect("Error: unable to request file.");
+            }
+        }
+    });
+
+    for entry in entries {
+        catalog.add_item(format!("{0} ({1} bytes)", entry.name, entry.size), entry);
+    }
+
+    siv.add_layer(
+        Dialog::around(catalog.with_name("catalog").scrollable().min_width(40))
+            .title(format!("shares on {0}", peer))
+            .button("by name", |s| {
+                s.call_on_name("catalog", |c: &mut SelectView<CatalogEntry>| {
+                    c.sort_by(|a, b| a.name.cmp(&b.name))
+                });
+            })
+            .button("by size", |s| {
+                s.call_on_name("catalog", |c: &mut SelectView<CatalogEntry>| {
+                    c.sort_by(|a, b| b.size.cmp(&a.size))
+                });
+            })
+            .dismiss_button("close"),
+    );
+}
@@ -0,0 +1,39 @@
+use cursive::{
+    views::{Dialog, EditView},
+    Cursive,
+};
+use std::time::Duration;
+
+use crate::session::Session;
+
+pub fn start_carousel(siv: &mut Cursive, session: &Session) {
+    siv.add_layer(
+        Dialog::around(EditView::new().on_submit({
+            let session = session.clone();
+            move |siv, entry: &str| {
+                siv.pop_layer();
+
+                let (minutes, file) = match entry.trim().split_once(' ') {
+                    Some((minutes, file)) => (minutes.parse::<u64>(), file.trim()),
+                    None => {
+                        siv.add_layer(Dialog::info("expected: <minutes> <file path>"));
+                        return;
+                    }
+                };
+
+                let seconds = match minutes.map(|m| m.checked_mul(60)) {
+                    Ok(Some(seconds)) if seconds > 0 => seconds,
+                    _ => {
+                        siv.add_layer(Dialog::info("invalid duration"));
+                        return;
+                    }
+                };
+
+                if let Err(e) = session.carousel(file, Duration::from_secs(seconds)) {
+                    siv.add_layer(Dialog::info(e.to_string()));
+                }
+            }
+        }))
+        .title("Enter minutes and file path to loop, e.g. 10 /tmp/slides.pdf"),
+    );
+}
@@ -0,0 +1,15 @@
+use arboard::Clipboard;
+
+use crate::errors::FloodFileError;
+
+pub fn copy(text: &str) -> Result<(), FloodFileError> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|_| FloodFileError::ClipboardError)
+}
+
+pub fn paste() -> Result<String, FloodFileError> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|_| FloodFileError::ClipboardError)
+}
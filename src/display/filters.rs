@@ -0,0 +1,103 @@
+use cursive::{
+    traits::{Nameable, Resizable, Scrollable},
+    views::{Dialog, EditView, LinearLayout, Panel, SelectView},
+    Cursive,
+};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::network::filter::{load_rules, save_rules, FilterRule};
+use crate::session::Session;
+
+// kept between runs in the user's config directory
+fn rules_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("floodfile").join("filters"))
+}
+
+pub fn load_filters(siv: &mut Cursive, session: &Session) {
+    let Some(path) = rules_path() else {
+        return;
+    };
+
+    match load_rules(&path) {
+        Ok(rules) => {
+            for rule in rules {
+                session
+                    .add_filter(rule)
+                    .expect("Error: unable to add filter.");
+            }
+        }
+        Err(e) => siv.add_layer(Dialog::info(e.to_string())),
+    }
+}
+
+fn save_filters(siv: &mut Cursive, session: &Session) {
+    let (Some(path), Ok(rules)) = (rules_path(), session.filters()) else {
+        return;
+    };
+
+    if let Err(e) = save_rules(&path, &rules) {
+        siv.add_layer(Dialog::info(e.to_string()));
+    }
+}
+
+fn refresh_rules(siv: &mut Cursive, session: &Session) {
+    let rules = session.filters().unwrap_or_default();
+    siv.call_on_name("filter_rules", |view: &mut SelectView<FilterRule>| {
+        view.clear();
+        view.add_all(rules.into_iter().map(|r| (r.to_string(), r)));
+    });
+}
+
+pub fn show_filters(siv: &mut Cursive, session: &Session) {
+    let rule_list = SelectView::<FilterRule>::new()
+        .on_submit({
+            let session = session.clone();
+            move |siv, rule: &FilterRule| {
+                session
+                    .remove_filter(*rule)
+                    .expect("Error: unable to remove filter.");
+                save_filters(siv, &session);
+                refresh_rules(siv, &session);
+            }
+        })
+        .with_name("filter_rules")
+        .scrollable()
+        .min_height(5);
+
+    let rule_input = EditView::new().on_submit({
+        let session = session.clone();
+        move |siv, rule: &str| {
+            siv.call_on_name("filter_input", |field: &mut EditView| field.set_content(""));
+
+            match FilterRule::from_str(rule) {
+                Ok(rule) => {
+                    session
+                        .add_filter(rule)
+                        .expect("Error: unable to add filter.");
+                    save_filters(siv, &session);
+                }
+                Err(e) => siv.add_layer(Dialog::info(e.to_string())),
+            };
+            refresh_rules(siv, &session);
+        }
+    });
+
+    siv.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(Panel::new(rule_list).title("rules (select to remove)"))
+                .child(
+                    Panel::new(rule_input.with_name("filter_input"))
+                        .title("<allow|deny> <advertise|request|file> <mac>"),
+                ),
+        )
+        .title("mac filters")
+        .dismiss_button("close")
+        .min_width(50),
+    );
+    refresh_rules(siv, session);
+}
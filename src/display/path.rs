@@ -1,17 +1,17 @@
-use crossbeam::channel::Sender;
 use cursive::{
     views::{Dialog, EditView},
     Cursive,
 };
 
-use crate::display::NetworkCommand;
+use crate::session::Session;
 
-pub fn change_path(siv: &mut Cursive, network_tx: &Sender<NetworkCommand>) {
+pub fn change_path(siv: &mut Cursive, session: &Session) {
     siv.add_layer(
         Dialog::around(EditView::new().on_submit({
-            let tx = network_tx.clone();
+            let session = session.clone();
             move |siv, name: &str| {
-                tx.send(NetworkCommand::UpdateLocalPath(name.to_string()))
+                session
+                    .set_download_dir(name)
                     .expect("Error: unable to update path.");
 
                 siv.pop_layer();
@@ -0,0 +1,136 @@
+use cursive::{
+    traits::{Nameable, Resizable, Scrollable},
+    views::{Dialog, EditView, LinearLayout, Panel, SelectView},
+    Cursive,
+};
+use pnet::util::MacAddr;
+use std::str::FromStr;
+
+use crate::session::push::{PushId, PushPolicy};
+use crate::session::Session;
+
+const POLICIES: [PushPolicy; 3] = [
+    PushPolicy::Ignore,
+    PushPolicy::Prompt,
+    PushPolicy::AcceptTrusted,
+];
+
+fn refresh_settings(siv: &mut Cursive, session: &Session) {
+    let (policy, trusted) = session.push_settings().unwrap_or_default();
+
+    siv.call_on_name("push_policy", |view: &mut SelectView<PushPolicy>| {
+        let selected = POLICIES
+            .iter()
+            .position(|p| *p == policy)
+            .unwrap_or_default();
+        view.set_selection(selected);
+    });
+    siv.call_on_name("push_trusted", |view: &mut SelectView<MacAddr>| {
+        view.clear();
+        view.add_all(trusted.into_iter().map(|mac| (mac.to_string(), mac)));
+    });
+}
+
+pub fn show_pushes(siv: &mut Cursive, session: &Session) {
+    let policy = SelectView::<PushPolicy>::new()
+        .with_all(POLICIES.iter().map(|p| (p.to_string(), *p)))
+        .on_submit({
+            let session = session.clone();
+            move |_siv, policy: &PushPolicy| {
+                session
+                    .set_push_policy(*policy)
+                    .expect("Error: unable to update push policy.");
+            }
+        })
+        .with_name("push_policy");
+
+    let trusted = SelectView::<MacAddr>::new()
+        .on_submit({
+            let session = session.clone();
+            move |siv, mac: &MacAddr| {
+                session
+                    .untrust(*mac)
+                    .expect("Error: unable to update trusted peers.");
+                refresh_settings(siv, &session);
+            }
+        })
+        .with_name("push_trusted")
+        .scrollable()
+        .min_height(3);
+
+    let trust_input = EditView::new().on_submit({
+        let session = session.clone();
+        move |siv, mac: &str| {
+            siv.call_on_name("push_trust_input", |field: &mut EditView| {
+                field.set_content("")
+            });
+
+            match MacAddr::from_str(mac.trim()) {
+                Ok(mac) => session
+                    .trust(mac)
+                    .expect("Error: unable to update trusted peers."),
+                Err(_) => siv.add_layer(Dialog::info("invalid mac address")),
+            };
+            refresh_settings(siv, &session);
+        }
+    });
+
+    let push_input = EditView::new().on_submit({
+        let session = session.clone();
+        move |siv, file: &str| {
+            siv.call_on_name("push_file_input", |field: &mut EditView| {
+                field.set_content("")
+            });
+
+            if let Err(e) = session.push(file.trim()) {
+                siv.add_layer(Dialog::info(e.to_string()));
+            }
+        }
+    });
+
+    siv.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(Panel::new(push_input.with_name("push_file_input")).title("push a file"))
+                .child(Panel::new(policy).title("incoming pushes (select to apply)"))
+                .child(Panel::new(trusted).title("trusted peers (select to remove)"))
+                .child(Panel::new(trust_input.with_name("push_trust_input")).title("trust <mac>")),
+        )
+        .title("pushes")
+        .dismiss_button("close")
+        .min_width(50),
+    );
+    refresh_settings(siv, session);
+}
+
+pub fn offer_push(
+    siv: &mut Cursive,
+    session: &Session,
+    id: PushId,
+    peer: MacAddr,
+    name: &str,
+    size: usize,
+) {
+    siv.add_layer(
+        Dialog::text(format!("{0} is pushing {1} ({2} bytes)", peer, name, size))
+            .title("incoming push")
+            .button("accept", {
+                let session = session.clone();
+                move |siv| {
+                    siv.pop_layer();
+                    session
+                        .accept_push(id)
+                        .expect("Error: unable to accept push.");
+                }
+            })
+            .button("reject", {
+                let session = session.clone();
+                move |siv| {
+                    siv.pop_layer();
+                    session
+                        .reject_push(id)
+                        .expect("Error: unable to reject push.");
+                }
+            }),
+    );
+}
@@ -0,0 +1,29 @@
+use cursive::{
+    views::{Dialog, EditView},
+    Cursive,
+};
+use std::path::PathBuf;
+
+use crate::session::Session;
+
+pub fn change_recording(siv: &mut Cursive, session: &Session) {
+    siv.add_layer(
+        Dialog::around(EditView::new().on_submit({
+            let session = session.clone();
+            move |siv, path: &str| {
+                // an empty entry stops the current recording
+                let path = match path.trim() {
+                    "" => None,
+                    path => Some(PathBuf::from(path)),
+                };
+
+                session
+                    .record(path)
+                    .expect("Error: unable to update recording.");
+
+                siv.pop_layer();
+            }
+        }))
+        .title("Enter pcapng path to record to, empty to stop"),
+    );
+}
@@ -0,0 +1,36 @@
+use cursive::{
+    views::{Dialog, EditView},
+    Cursive,
+};
+use std::str::FromStr;
+
+use crate::network::relay::RelayConfig;
+use crate::session::Session;
+
+pub fn change_relay(siv: &mut Cursive, session: &Session) {
+    siv.add_layer(
+        Dialog::around(EditView::new().on_submit({
+            let session = session.clone();
+            move |siv, relay: &str| {
+                siv.pop_layer();
+
+                // an empty entry stops relaying
+                let relay = match relay.trim() {
+                    "" => None,
+                    relay => match RelayConfig::from_str(relay) {
+                        Ok(relay) => Some(relay),
+                        Err(e) => {
+                            siv.add_layer(Dialog::info(e.to_string()));
+                            return;
+                        }
+                    },
+                };
+
+                session
+                    .set_relay(relay)
+                    .expect("Error: unable to update relay.");
+            }
+        }))
+        .title("Enter max hops[:frames per second], empty to stop relaying"),
+    );
+}
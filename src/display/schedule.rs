@@ -0,0 +1,102 @@
+use chrono::{Local, NaiveTime, TimeDelta};
+use cursive::{
+    traits::Nameable,
+    views::{Dialog, EditView, LinearLayout, TextView},
+    Cursive,
+};
+use std::time::{Duration, SystemTime};
+
+use crate::session::{Session, ShareOptions};
+
+pub fn schedule_share(siv: &mut Cursive, session: &Session) {
+    let field = |label: &str, name: &str| {
+        LinearLayout::horizontal()
+            .child(TextView::new(format!("{0:<14}", label)))
+            .child(EditView::new().with_name(name))
+    };
+
+    siv.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(field("file path", "schedule_path"))
+                .child(field("start (HH:MM)", "schedule_start"))
+                .child(field("minutes", "schedule_minutes"))
+                .child(field("max downloads", "schedule_max")),
+        )
+        .title("Schedule a share, empty fields are unlimited")
+        .button("share", {
+            let session = session.clone();
+            move |siv| {
+                let text = |siv: &mut Cursive, name: &str| {
+                    siv.call_on_name(name, |v: &mut EditView| v.get_content().trim().to_string())
+                        .unwrap_or_default()
+                };
+
+                let path = text(siv, "schedule_path");
+                let options = match parse_options(
+                    &text(siv, "schedule_start"),
+                    &text(siv, "schedule_minutes"),
+                    &text(siv, "schedule_max"),
+                ) {
+                    Some(options) => options,
+                    None => {
+                        siv.add_layer(Dialog::info("invalid schedule"));
+                        return;
+                    }
+                };
+
+                siv.pop_layer();
+                if let Err(e) = session.share_with(&path, options) {
+                    siv.add_layer(Dialog::info(e.to_string()));
+                }
+            }
+        })
+        .dismiss_button("cancel"),
+    );
+}
+
+fn parse_options(start: &str, minutes: &str, max: &str) -> Option<ShareOptions> {
+    let start_at = match start {
+        "" => None,
+        start => Some(next_occurrence(
+            NaiveTime::parse_from_str(start, "%H:%M").ok()?,
+        )?),
+    };
+
+    let stop_after = match minutes {
+        "" => None,
+        minutes => match minutes.parse::<u64>().ok()? {
+            0 => return None,
+            minutes => Some(Duration::from_secs(minutes.checked_mul(60)?)),
+        },
+    };
+
+    let max_downloads = match max {
+        "" => None,
+        max => match max.parse::<u32>().ok()? {
+            0 => return None,
+            max => Some(max),
+        },
+    };
+
+    Some(ShareOptions {
+        start_at,
+        stop_after,
+        max_downloads,
+    })
+}
+
+// the next time the local clock reads this, today or tomorrow
+fn next_occurrence(time: NaiveTime) -> Option<SystemTime> {
+    let now = Local::now();
+    let mut at = now
+        .date_naive()
+        .and_time(time)
+        .and_local_timezone(Local)
+        .earliest()?;
+    if at <= now {
+        at += TimeDelta::days(1);
+    }
+
+    Some(at.into())
+}
@@ -0,0 +1,90 @@
+use cursive::{
+    traits::{Nameable, Resizable, Scrollable},
+    views::{Dialog, LinearLayout, Panel, SelectView, TextArea},
+    Cursive,
+};
+
+#[cfg(feature = "clipboard")]
+use crate::display::clipboard;
+use crate::session::Session;
+
+fn refresh_snippets(siv: &mut Cursive, session: &Session) {
+    let snippets = session.snippets().unwrap_or_default();
+    siv.call_on_name("snippet_list", |view: &mut SelectView<String>| {
+        view.clear();
+        view.add_all(snippets.into_iter().rev().map(|s| {
+            let first = s.text.lines().next().unwrap_or_default();
+            (format!("{0}: {1}", s.peer, first), s.text)
+        }));
+    });
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_snippet(siv: &mut Cursive, text: &str) {
+    match clipboard::copy(text) {
+        Ok(_) => siv.add_layer(Dialog::info("copied to clipboard")),
+        Err(e) => siv.add_layer(Dialog::info(e.to_string())),
+    }
+}
+
+// no clipboard support, shown on its own so the terminal can select it
+#[cfg(not(feature = "clipboard"))]
+fn copy_snippet(siv: &mut Cursive, text: &str) {
+    siv.add_layer(
+        Dialog::around(cursive::views::TextView::new(text).scrollable())
+            .title("snippet")
+            .dismiss_button("close"),
+    );
+}
+
+pub fn show_snippets(siv: &mut Cursive, session: &Session) {
+    let snippet_list = SelectView::<String>::new()
+        .on_submit(|siv, text: &String| copy_snippet(siv, text))
+        .with_name("snippet_list")
+        .scrollable()
+        .min_height(5);
+
+    let dialog = Dialog::around(
+        LinearLayout::vertical()
+            .child(Panel::new(snippet_list).title("received (select to copy)"))
+            .child(
+                Panel::new(TextArea::new().with_name("snippet_input").min_height(3))
+                    .title("type or paste text to send"),
+            ),
+    )
+    .title("snippets")
+    .button("send", {
+        let session = session.clone();
+        move |siv| {
+            let text = siv
+                .call_on_name("snippet_input", |view: &mut TextArea| {
+                    view.get_content().to_string()
+                })
+                .unwrap_or_default();
+
+            if text.trim().is_empty() {
+                return;
+            }
+
+            match session.send_snippet(&text) {
+                Ok(_) => {
+                    siv.call_on_name("snippet_input", |view: &mut TextArea| view.set_content(""));
+                }
+                Err(e) => siv.add_layer(Dialog::info(e.to_string())),
+            }
+        }
+    });
+
+    #[cfg(feature = "clipboard")]
+    let dialog = dialog.button("paste", |siv| match clipboard::paste() {
+        Ok(text) => {
+            siv.call_on_name("snippet_input", |view: &mut TextArea| {
+                view.set_content(text)
+            });
+        }
+        Err(e) => siv.add_layer(Dialog::info(e.to_string())),
+    });
+
+    siv.add_layer(dialog.dismiss_button("close").min_width(50));
+    refresh_snippets(siv, session);
+}
@@ -2,12 +2,17 @@ use crossbeam::channel::Sender;
 use cursive::{
     traits::{Nameable, Resizable, Scrollable},
     view::ScrollStrategy,
-    views::{Dialog, EditView, LinearLayout, NamedView, Panel, SelectView},
+    views::{Dialog, EditView, LinearLayout, NamedView, Panel, SelectView, TextView},
     Cursive,
 };
 
+use pnet::datalink::NetworkInterface;
+use std::path::Path;
+use std::time::Instant;
+
 use crate::display::DisplayCommand;
-use crate::network::utils::usable_interfaces;
+use crate::network::utils::link_up;
+use crate::session::LocalShare;
 
 fn create_file_input(display_tx: &Sender<DisplayCommand>) -> Panel<NamedView<EditView>> {
     Panel::new(
@@ -25,29 +30,54 @@ fn create_file_input(display_tx: &Sender<DisplayCommand>) -> Panel<NamedView<Edi
     .title("file path")
 }
 
-fn create_interface_select(display_tx: &Sender<DisplayCommand>) -> Panel<SelectView> {
+fn interface_label(interface: &NetworkInterface, active: bool) -> String {
+    let mac = interface
+        .mac
+        .expect("Error: interface missing mac address.");
+
+    let label = match active {
+        true => format!("[x] {0}: {1}", interface.name, mac),
+        false => format!("[ ] {0}: {1}", interface.name, mac),
+    };
+
+    match link_up(interface) {
+        true => label,
+        false => format!("{0} (down)", label),
+    }
+}
+
+fn create_interface_select(display_tx: &Sender<DisplayCommand>) -> Panel<NamedView<SelectView>> {
     Panel::new(
         SelectView::new()
-            .with_all(usable_interfaces().into_iter().map(|i| {
-                (
-                    format!(
-                        "{0}: {1}",
-                        i.name,
-                        i.mac.expect("Error: interface missing mac address.")
-                    ),
-                    i.name,
-                )
-            }))
             .on_submit({
                 let tx = display_tx.clone();
                 move |_, name: &String| {
-                    tx.send(DisplayCommand::ChangeInterface(name.to_string()))
+                    tx.send(DisplayCommand::ToggleInterface(name.to_string()))
                         .expect("Error: unable to change interface.")
                 }
-            }),
+            })
+            .with_name("interfaces"),
     )
 }
 
+pub fn refresh_interfaces(siv: &mut Cursive, interfaces: Vec<NetworkInterface>, active: &[String]) {
+    siv.call_on_name("interfaces", |view: &mut SelectView| {
+        // keep the cursor on the same interface if it's still there
+        let selected = view.selection();
+        view.clear();
+        view.add_all(interfaces.iter().map(|i| {
+            let label = interface_label(i, active.contains(&i.name));
+            (label, i.name.clone())
+        }));
+
+        if let Some(selected) = selected {
+            if let Some(idx) = interfaces.iter().position(|i| i.name == *selected) {
+                view.set_selection(idx);
+            }
+        }
+    });
+}
+
 pub fn start_ui(siv: &mut Cursive, display_tx: Sender<DisplayCommand>) {
     siv.add_fullscreen_layer(
         LinearLayout::horizontal()
@@ -60,7 +90,12 @@ pub fn start_ui(siv: &mut Cursive, display_tx: Sender<DisplayCommand>) {
                     )
                     .child(
                         create_interface_select(&display_tx)
-                            .title("interfaces")
+                            .title("interfaces (select to toggle)")
+                            .full_height(),
+                    )
+                    .child(
+                        Panel::new(TextView::new("").with_name("my_shares").scrollable())
+                            .title("my shares")
                             .full_height(),
                     ),
             )
@@ -77,3 +112,46 @@ pub fn start_ui(siv: &mut Cursive, display_tx: Sender<DisplayCommand>) {
             ),
     );
 }
+
+pub fn refresh_my_shares(siv: &mut Cursive, shares: &[LocalShare]) {
+    let mut lines: Vec<String> = shares.iter().map(share_status).collect();
+    lines.sort();
+
+    siv.call_on_name("my_shares", |view: &mut TextView| {
+        view.set_content(lines.join("\n"));
+    });
+}
+
+fn share_status(share: &LocalShare) -> String {
+    let name = Path::new(&share.path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| share.path.clone());
+
+    let mut status = vec![];
+    if let Some(starts) = share.starts {
+        status.push(format!("starts in {0}", countdown(starts)));
+    }
+    if let Some(expires) = share.expires {
+        status.push(format!("stops in {0}", countdown(expires)));
+    }
+    match share.max_downloads {
+        Some(max) => status.push(format!("{0}/{1} downloads", share.downloads, max)),
+        None if share.downloads > 0 => status.push(format!("{0} downloads", share.downloads)),
+        None => (),
+    }
+
+    match status.is_empty() {
+        true => name,
+        false => format!("{0}: {1}", name, status.join(", ")),
+    }
+}
+
+fn countdown(at: Instant) -> String {
+    let secs = at.saturating_duration_since(Instant::now()).as_secs();
+    match secs {
+        0..60 => format!("{0}s", secs),
+        60..3600 => format!("{0}m{1:02}s", secs / 60, secs % 60),
+        _ => format!("{0}h{1:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,36 @@
+use cursive::{
+    views::{Dialog, EditView},
+    Cursive,
+};
+use std::str::FromStr;
+
+use crate::network::Vlan;
+use crate::session::Session;
+
+pub fn change_vlan(siv: &mut Cursive, session: &Session) {
+    siv.add_layer(
+        Dialog::around(EditView::new().on_submit({
+            let session = session.clone();
+            move |siv, vlan: &str| {
+                siv.pop_layer();
+
+                // an empty entry goes back to untagged frames
+                let vlan = match vlan.trim() {
+                    "" => None,
+                    vlan => match Vlan::from_str(vlan) {
+                        Ok(vlan) => Some(vlan),
+                        Err(e) => {
+                            siv.add_layer(Dialog::info(e.to_string()));
+                            return;
+                        }
+                    },
+                };
+
+                session
+                    .set_vlan(vlan)
+                    .expect("Error: unable to update vlan.");
+            }
+        }))
+        .title("Enter vlan id[:priority], empty for untagged"),
+    );
+}
@@ -14,18 +14,76 @@ pub enum FloodFileError {
     #[error("the provided packet is too large to send.")]
     PacketTooLarge,
 
+    #[error("snippets are limited to 4096 bytes.")]
+    SnippetTooLarge,
+
+    #[error("snippets can't be empty.")]
+    EmptySnippet,
+
+    #[error("unable to access the clipboard.")]
+    ClipboardError,
+
     #[error("unable to send ARP packet over the wire.")]
     FailedToSendArp,
 
+    #[error("the datalink send buffer is full.")]
+    SendBufferFull,
+
+    #[error("transfer interrupted at chunk {offset} after {errors} send errors.")]
+    TransferInterrupted {
+        key: crate::network::Key,
+        offset: u16,
+        errors: u32,
+    },
+
     #[error("unable to serialize ARP packet.")]
     FailedToSerializeArp,
 
     #[error("unable to deserialize ARP packet.")]
     FailedToDeserializeArp,
 
+    #[error("received chunk does not match its reassembly state.")]
+    ReassemblyMismatch,
+
     #[error("unable to generate file-hash.")]
     UnableToGenerateHash,
 
     #[error("invalid path to save files.")]
     InvalidDestinationPath,
+
+    #[error("unable to read shared file.")]
+    FailedToReadFile,
+
+    #[error("unable to write downloaded file.")]
+    FailedToWriteFile,
+
+    #[error("unable to read or write capture file.")]
+    CaptureError(#[from] pcap_file::PcapError),
+
+    #[error("no usable network interface with that name.")]
+    InterfaceNotFound,
+
+    #[error("the session has shut down.")]
+    SessionClosed,
+
+    #[error("timed out waiting on the other end of the stream.")]
+    StreamTimedOut,
+
+    #[error("joined the stream too late, the sender no longer has its start.")]
+    StreamRefused,
+
+    #[error("not every receiver confirmed the end of the stream.")]
+    StreamIncomplete,
+
+    #[error("invalid vlan, expected: <id 1-4094>[:<priority 0-7>].")]
+    InvalidVlan,
+
+    #[error("invalid filter rule, expected: <allow|deny> <advertise|request|file> <mac>.")]
+    InvalidFilterRule,
+
+    #[error("unable to read or write the filter rules file.")]
+    FilterFileError,
+
+    #[error("invalid relay settings, expected: <max hops>[:<frames per second>].")]
+    InvalidRelay,
 }
@@ -0,0 +1,68 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::prelude::*;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::errors::FloodFileError;
+
+pub fn read_share(path: &str) -> Result<Vec<u8>, FloodFileError> {
+    fs::read(path).map_err(|_| FloodFileError::FailedToReadFile)
+}
+
+pub fn share_size(path: &str) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+// renamed copies tried before giving up on a name that's taken
+const MAX_RENAMES: u32 = 100;
+
+fn file_name(name: &str) -> Result<PathBuf, FloodFileError> {
+    // extract only the filename, never trust a remote path
+    PathBuf::from(name)
+        .file_name()
+        .map(PathBuf::from)
+        .ok_or(FloodFileError::InvalidDestinationPath)
+}
+
+pub fn save_download(dir: &Path, name: &str, data: &[u8]) -> Result<PathBuf, FloodFileError> {
+    let filename = file_name(name)?;
+
+    // destination path + filename
+    let path = dir.join(filename);
+
+    // write file to disk!
+    let mut file = File::create(&path).map_err(|_| FloodFileError::FailedToWriteFile)?;
+    file.write_all(data)
+        .map_err(|_| FloodFileError::FailedToWriteFile)?;
+
+    Ok(path)
+}
+
+// like save_download, but never replaces an existing file: "notes.txt" becomes
+// "notes (1).txt" and so on
+pub fn save_new(dir: &Path, name: &str, data: &[u8]) -> Result<PathBuf, FloodFileError> {
+    let filename = file_name(name)?;
+    let stem = filename.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = filename.extension().map(|e| e.to_string_lossy());
+
+    for copy in 0..=MAX_RENAMES {
+        let path = match (copy, &extension) {
+            (0, _) => dir.join(&filename),
+            (_, Some(extension)) => dir.join(format!("{0} ({1}).{2}", stem, copy, extension)),
+            (_, None) => dir.join(format!("{0} ({1})", stem, copy)),
+        };
+
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(_) => return Err(FloodFileError::FailedToWriteFile),
+        };
+        file.write_all(data)
+            .map_err(|_| FloodFileError::FailedToWriteFile)?;
+
+        return Ok(path);
+    }
+
+    Err(FloodFileError::FailedToWriteFile)
+}
@@ -1,3 +1,7 @@
+pub mod cli;
 pub mod display;
 pub mod errors;
+pub mod files;
 pub mod network;
+pub mod protocol;
+pub mod session;
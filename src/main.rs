@@ -1,3 +1,13 @@
-fn main() {
-    floodfile::display::start();
+use clap::Parser;
+use floodfile::cli::Cli;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match Cli::parse().command {
+        Some(command) => floodfile::cli::run(command),
+        None => {
+            floodfile::display::start();
+            ExitCode::SUCCESS
+        }
+    }
 }
@@ -1,63 +1,146 @@
 use crossbeam::channel::{unbounded, Receiver, Sender};
+use frame::Frame;
 use payload::Payload;
 use pnet::datalink::Channel::Ethernet;
-use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
-use pnet::packet::Packet;
 use pnet::{
     datalink::{DataLinkReceiver, DataLinkSender, NetworkInterface},
     util::MacAddr,
 };
-use rand::prelude::*;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::io::ErrorKind;
+use std::path::Path;
 use std::str::FromStr;
-use std::thread;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use crate::errors::FloodFileError;
-
+use crate::protocol::{Event, Protocol};
+use capture::Recorder;
+use pacing::Pacer;
+use reassembly::LossReport;
+use scheduler::{Priority, Scheduler};
+
+#[cfg(feature = "async")]
+pub mod async_channel;
+pub mod capture;
+pub mod filter;
+pub mod frame;
+pub mod loopback;
+pub mod monitor;
+pub mod pacing;
 pub mod payload;
+pub mod reassembly;
+pub mod relay;
+pub mod scheduler;
+pub mod stream;
 pub mod utils;
 
-const ETHERNET_HEADER_SIZE: usize = 14;
-const ETHERNET_PACKET_SIZE: usize = 1518;
-// bytes: [preamble (4)] + [opcode(1), total (2), offset (2)] + [key (8)]
-const FLOODFILE_HEADER_SIZE: usize = MSG_PREAMBLE.len() + 5 + 8;
-const MSG_PREAMBLE: &[u8] = b"file";
-const CHUNK_SIZE: usize = u8::MAX as usize - FLOODFILE_HEADER_SIZE;
+pub const ETHERNET_PACKET_SIZE: usize = 1518;
+
+// how often a blocked listener wakes up to check whether it should stop
+const LISTENER_READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+// transient send failures are retried with a doubling backoff up to this many times
+pub const MAX_SEND_RETRIES: u32 = 6;
+const SEND_BACKOFF: Duration = Duration::from_millis(2);
+
+pub fn send_backoff(retries: u32) -> Duration {
+    SEND_BACKOFF * 2u32.pow(retries.min(MAX_SEND_RETRIES))
+}
 
 pub type Key = [u8; 8];
+pub type Nonce = [u8; 4];
 pub type FileHash = [u8; 16];
+pub type StreamId = [u8; 16];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vlan {
+    pub id: u16,
+    pub priority: u8,
+}
+
+// format: "<id>" or "<id>:<priority>"
+impl FromStr for Vlan {
+    type Err = FloodFileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, priority) = s.trim().split_once(':').unwrap_or((s.trim(), "0"));
+        let id = id.parse::<u16>().map_err(|_| FloodFileError::InvalidVlan)?;
+        let priority = priority
+            .parse::<u8>()
+            .map_err(|_| FloodFileError::InvalidVlan)?;
+
+        // 0 and 4095 are reserved, priority is a 3 bit field
+        if id == 0 || id >= 4095 || priority > 7 {
+            return Err(FloodFileError::InvalidVlan);
+        }
+
+        Ok(Vlan { id, priority })
+    }
+}
 
 fn listener_thread(
     mut channel_rx: Box<dyn DataLinkReceiver>,
     buffer_tx: Sender<[u8; ETHERNET_PACKET_SIZE]>,
+    running: Arc<AtomicBool>,
 ) {
     let mut buffer = [0u8; 1518];
-    loop {
+    while running.load(Ordering::Relaxed) {
         let data = match channel_rx.next() {
             Ok(packet) => packet,
-            _ => continue,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => {
+                // don't spin on a socket that keeps failing
+                log::warn!("listener read failed: {e}");
+                thread::sleep(LISTENER_READ_TIMEOUT);
+                continue;
+            }
         };
 
         let len = data.len().min(1518);
         buffer[..len].copy_from_slice(&data[..len]);
 
-        buffer_tx.send(buffer).ok();
+        // nobody left to read frames, the channel is gone
+        if buffer_tx.send(buffer).is_err() {
+            return;
+        }
+    }
+}
+
+// the receiving thread behind a Channel, stopped and joined when dropped
+struct Listener {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
     }
 }
 
 pub struct Channel {
-    src_mac_addr: MacAddr,
-    local_path: PathBuf,
+    protocol: Protocol,
+    scheduler: Scheduler,
+    retry_at: Option<Instant>,
+    pacer: Pacer,
+    recorder: Option<Recorder>,
     interface: NetworkInterface,
     tx: Box<dyn DataLinkSender>,
     buffer_rx: Receiver<[u8; ETHERNET_PACKET_SIZE]>,
-    packets: HashMap<Key, Vec<Vec<u8>>>,
+    listener: Option<Listener>,
 }
 
 impl Channel {
     pub fn new(interface: NetworkInterface) -> Result<Self, FloodFileError> {
-        let config = pnet::datalink::Config::default();
+        let config = pnet::datalink::Config {
+            read_timeout: Some(LISTENER_READ_TIMEOUT),
+            ..Default::default()
+        };
         let (tx, rx) = match pnet::datalink::channel(&interface, config) {
             Ok(Ethernet(tx, rx)) => (tx, rx),
             Ok(_) => return Err(FloodFileError::InvalidChannelType),
@@ -65,43 +148,219 @@ impl Channel {
         };
 
         let (buffer_tx, buffer_rx) = unbounded::<[u8; ETHERNET_PACKET_SIZE]>();
-        thread::spawn(move || listener_thread(rx, buffer_tx)); // detached
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = thread::spawn({
+            let running = running.clone();
+            move || listener_thread(rx, buffer_tx, running)
+        });
+
+        let mut channel = Self::from_parts(interface, tx, buffer_rx);
+        channel.listener = Some(Listener {
+            running,
+            handle: Some(handle),
+        });
+
+        Ok(channel)
+    }
 
-        Ok(Self {
-            src_mac_addr: interface
-                .mac
-                .expect("Error: interface missing mac address."),
-            local_path: std::env::temp_dir(),
+    fn from_parts(
+        interface: NetworkInterface,
+        tx: Box<dyn DataLinkSender>,
+        buffer_rx: Receiver<[u8; ETHERNET_PACKET_SIZE]>,
+    ) -> Self {
+        Self {
+            protocol: Protocol::new(
+                interface
+                    .mac
+                    .expect("Error: interface missing mac address."),
+            ),
+            scheduler: Scheduler::default(),
+            retry_at: None,
+            pacer: Pacer::default(),
+            recorder: None,
             interface,
             tx,
             buffer_rx,
-            packets: HashMap::new(),
-        })
+            listener: None,
+        }
     }
 
     pub fn send(&mut self, packet: Payload) -> Result<(), FloodFileError> {
-        let data = packet.serialize();
+        self.resume(packet, rand::random(), 0)
+    }
 
-        // chunk packet into maximum possible size
-        let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+    // sends a payload under a given key starting at offset, so a transfer that
+    // failed with TransferInterrupted can pick up where it stopped
+    pub fn resume(&mut self, packet: Payload, key: Key, offset: u16) -> Result<(), FloodFileError> {
+        let frames = self.protocol.encode_with_key(&packet, key)?;
+        let mut errors = 0;
 
-        // structure: [[opcode], [total], [offset], [payload specific data]]
-        let opcode = packet.opcode();
-        let total = chunks.len();
-        let key: Key = rand::thread_rng().gen();
+        // send chunks over wire!
+        for (offset, frame) in frames.iter().enumerate().skip(offset as usize) {
+            let mut retries = 0;
+            loop {
+                match self.transmit(frame) {
+                    Ok(()) => break,
+                    Err(FloodFileError::SendBufferFull) if retries < MAX_SEND_RETRIES => {
+                        thread::sleep(send_backoff(retries));
+                        retries += 1;
+                        errors += 1;
+                    }
+                    Err(_) => {
+                        return Err(FloodFileError::TransferInterrupted {
+                            key,
+                            offset: offset as u16,
+                            errors: errors + 1,
+                        })
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 
-        if total >= u16::MAX as usize {
-            return Err(FloodFileError::FileTooLarge);
+    // like send, but the frames wait in the scheduler until pumped out
+    pub fn queue(&mut self, packet: Payload) -> Result<(), FloodFileError> {
+        self.queue_with_key(packet, rand::random())
+    }
+
+    // queues under a key of the caller's choosing, to look it up with `queued` later
+    pub fn queue_with_key(&mut self, packet: Payload, key: Key) -> Result<(), FloodFileError> {
+        let frames = self.protocol.encode_with_key(&packet, key)?;
+        self.scheduler.push(key, Priority::of(&packet), frames);
+        Ok(())
+    }
+
+    // sends up to budget queued frames, returning how many went out
+    pub fn pump(&mut self, budget: usize) -> Result<usize, FloodFileError> {
+        if self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return Ok(0);
+        }
+        self.retry_at = None;
+
+        for sent in 0..budget {
+            if !self.pacer.ready(Instant::now()) {
+                return Ok(sent);
+            }
+
+            let outgoing = match self.scheduler.pop() {
+                Some(next) => next,
+                None => return Ok(sent),
+            };
+
+            match self.transmit(&outgoing.frame) {
+                Ok(()) => self.pacer.sent(Instant::now()),
+
+                // a full buffer drains by itself, back off and try the same frame again
+                Err(FloodFileError::SendBufferFull) if outgoing.retries < MAX_SEND_RETRIES => {
+                    self.retry_at = Some(Instant::now() + send_backoff(outgoing.retries));
+                    self.scheduler.retry(outgoing);
+                    return Ok(sent);
+                }
+
+                // held from this frame on until requeued, so it can carry on from here
+                Err(_) => {
+                    let error = FloodFileError::TransferInterrupted {
+                        key: outgoing.key,
+                        offset: outgoing.offset,
+                        errors: outgoing.errors + 1,
+                    };
+                    self.scheduler.interrupt(outgoing);
+                    return Err(error);
+                }
+            }
         }
 
-        // send chunks over wire!
-        for (offset, chunk) in chunks.iter().enumerate() {
-            self.send_chunk(opcode, offset as u16, total as u16, key, chunk)?;
+        Ok(budget)
+    }
+
+    // resumes a queued transfer that stopped with TransferInterrupted
+    pub fn requeue(&mut self, key: &Key) -> bool {
+        self.scheduler.requeue(key)
+    }
+
+    pub fn interrupted(&self) -> Vec<Key> {
+        self.scheduler.interrupted()
+    }
+
+    // carries another channel's queue over, e.g. onto a fresh socket for the
+    // same interface. the frames are already encoded with its address.
+    pub fn take_queue(&mut self, other: &mut Channel) {
+        self.scheduler = std::mem::take(&mut other.scheduler);
+    }
+
+    // drops a queued transfer, including one set aside after a send error
+    pub fn cancel(&mut self, key: &Key) {
+        self.scheduler.cancel(key);
+    }
+
+    // leaves only control frames queued, e.g. the withdrawals sent on shutdown
+    pub fn discard_transfers(&mut self) {
+        self.scheduler.discard_below(Priority::Control);
+    }
+
+    pub fn queued(&self, key: &Key) -> bool {
+        self.scheduler.contains(key)
+    }
+
+    pub fn pending(&self) -> bool {
+        !self.scheduler.is_empty()
+    }
+
+    // when pump next has something to do, if anything is queued
+    pub fn next_send(&self) -> Option<Instant> {
+        match self.pending() {
+            true => Some(
+                self.retry_at
+                    .map_or(self.pacer.next(), |at| at.max(self.pacer.next())),
+            ),
+            false => None,
         }
+    }
 
+    // queues a single chunk, for callers that slice payloads up themselves
+    pub fn queue_chunk(
+        &mut self,
+        op: u8,
+        offset: u16,
+        total: u16,
+        key: Key,
+        data: &[u8],
+        priority: Priority,
+    ) -> Result<(), FloodFileError> {
+        let frame = self.protocol.frame(op, offset, total, key, data)?;
+        self.scheduler.push(key, priority, vec![frame]);
         Ok(())
     }
 
+    // feedback from a receiver on one of our queued transfers
+    pub fn report_loss(&mut self, key: Key, received: u16, expected: u16) {
+        if self.scheduler.contains(&key) {
+            self.pacer.report(received, expected, Instant::now());
+        }
+    }
+
+    // what arrived of each unfinished incoming transfer since the last call
+    pub fn loss_reports(&mut self) -> Vec<LossReport> {
+        self.protocol.loss_reports()
+    }
+
+    // forgets incoming transfers that stopped arriving, returning how many
+    pub fn prune(&mut self, now: Instant) -> usize {
+        self.protocol.prune(now)
+    }
+
+    // whether any incoming transfer is part way through
+    pub fn receiving(&self) -> bool {
+        self.protocol.receiving()
+    }
+
+    // frames per second queued transfers are currently paced at
+    pub fn send_rate(&self) -> u32 {
+        self.pacer.rate()
+    }
+
     pub fn send_chunk(
         &mut self,
         op: u8,
@@ -110,110 +369,103 @@ impl Channel {
         key: Key,
         data: &[u8],
     ) -> Result<(), FloodFileError> {
-        let data = [
-            MSG_PREAMBLE,
-            &[op],
-            &offset.to_le_bytes()[..],
-            &total.to_le_bytes()[..],
-            &key[..],
-            data,
-        ]
-        .concat();
-
-        if data.len() > u8::MAX as usize {
-            return Err(FloodFileError::PacketTooLarge);
-        }
+        let frame = self.protocol.frame(op, offset, total, key, data)?;
+        self.transmit(&frame)
+    }
 
-        let arp_packet = [
-            &[0, 1],                     // hardware type
-            &[8, 0],                     // protocol type
-            &[6][..],                    // hardware size
-            &[data.len() as u8],         // payload length
-            &[0, 1],                     // opcode - req
-            &self.src_mac_addr.octets(), // sender mac
-            &data,                       // payload!
-            &[0; 6],                     // target mac
-            &data,                       // payload!
-        ]
-        .concat();
-
-        let mut ethernet_buffer = vec![0; ETHERNET_HEADER_SIZE + arp_packet.len()];
-        let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer)
-            .ok_or(FloodFileError::FailedToSerializeArp)?;
-        ethernet_packet.set_source(self.src_mac_addr);
-        ethernet_packet.set_destination(MacAddr::broadcast());
-        ethernet_packet.set_ethertype(EtherTypes::Arp);
-        ethernet_packet.set_payload(&arp_packet);
-
-        match self.tx.send_to(ethernet_packet.packet(), None) {
-            Some(Ok(())) => Ok(()),
-            _ => Err(FloodFileError::FailedToSendArp),
+    fn transmit(&mut self, frame: &[u8]) -> Result<(), FloodFileError> {
+        match self.tx.send_to(frame, None) {
+            Some(Ok(())) => (),
+            Some(Err(e)) if utils::buffer_full(&e) => return Err(FloodFileError::SendBufferFull),
+            _ => return Err(FloodFileError::FailedToSendArp),
         }
-    }
 
-    pub fn recv(&mut self) -> Result<Option<Payload>, FloodFileError> {
-        let data = match self.buffer_rx.try_recv() {
-            Ok(data) => data,
-            _ => return Ok(None),
-        };
+        // only what actually went out ends up in the capture
+        self.capture(frame);
 
-        let packet = match EthernetPacket::new(&data) {
-            Some(packet) => packet,
-            _ => return Ok(None),
-        };
+        Ok(())
+    }
 
-        if packet.get_ethertype() != EtherTypes::Arp || packet.payload()[7] != 1 {
-            return Ok(None);
-        }
-        if &packet.payload()[14..18] != MSG_PREAMBLE {
-            return Ok(None);
+    // a broken capture stops recording, it never costs a frame
+    fn capture(&mut self, frame: &[u8]) {
+        if let Some(Err(e)) = self.recorder.as_mut().map(|r| r.write(frame)) {
+            log::warn!("capture stopped: {e}");
+            self.recorder = None;
         }
+    }
 
-        let data_len = packet.payload()[5] as usize - MSG_PREAMBLE.len();
-        let data = &packet.payload()[18..(18 + data_len)];
+    pub fn recv(&mut self) -> Result<Option<(MacAddr, Payload)>, FloodFileError> {
+        match self.poll()? {
+            Some(Event::Received(source, packet)) => Ok(Some((source, packet))),
+            _ => Ok(None),
+        }
+    }
 
-        let opcode = data[0];
-        let offset = u16::from_le_bytes([data[1], data[2]]) as usize;
-        let total = u16::from_le_bytes([data[3], data[4]]) as usize;
-        let key: Key = match data[5..13].try_into() {
-            Ok(key) => key,
-            _ => return Ok(None),
-        };
+    pub fn poll(&mut self) -> Result<Option<Event>, FloodFileError> {
+        match self.buffer_rx.try_recv() {
+            Ok(data) => self.process(&data),
+            _ => Ok(None),
+        }
+    }
 
-        // allocate vec with total size
-        let packet = self.packets.entry(key).or_insert(vec![vec![]; total]);
+    // raw frames as they arrive, for callers that block or select on them
+    // rather than polling. hand each one to `process`.
+    pub fn frames(&self) -> Receiver<[u8; ETHERNET_PACKET_SIZE]> {
+        self.buffer_rx.clone()
+    }
 
-        // store portion if we haven't already
-        if packet[offset].is_empty() {
-            packet[offset] = data[13..].to_vec();
+    pub fn process(&mut self, data: &[u8]) -> Result<Option<Event>, FloodFileError> {
+        // trim the fixed size buffer down to the floodfile frame
+        if self.recorder.is_some() {
+            if let Some((_, len)) = Frame::decode(data) {
+                self.capture(&data[..len]);
+            }
         }
 
-        let collected: bool = packet.iter().all(|x| !x.is_empty());
-        if !collected {
-            return Ok(None);
-        }
+        self.protocol.decode(data)
+    }
 
-        let packet = Payload::deserialize(opcode, &packet[..].concat())
-            .ok_or(FloodFileError::FailedToDeserializeArp)?;
-        self.packets.remove(&key);
+    // queues a frame from another segment as-is, apart from our own ethernet
+    // address and vlan tag
+    pub fn forward(&mut self, frame: &Frame) -> Result<(), FloodFileError> {
+        let frame = Frame {
+            relayed_by: Some(self.mac_addr()),
+            vlan: self.vlan(),
+            ..frame.clone()
+        };
+
+        let priority = Priority::of_opcode(frame.opcode);
+        self.scheduler
+            .push(frame.key, priority, vec![frame.encode()?]);
+        Ok(())
+    }
+
+    pub fn accepts(&self, frame: &Frame) -> bool {
+        self.protocol.accepts(frame)
+    }
 
-        Ok(Some(packet))
+    pub fn mac_addr(&self) -> MacAddr {
+        self.protocol.mac_addr()
     }
 
     pub fn interface_name(&self) -> String {
         self.interface.name.clone()
     }
 
-    pub fn set_path(&mut self, path: &String) -> Result<(), FloodFileError> {
-        self.local_path = match PathBuf::from_str(path) {
-            Ok(path) => path,
-            Err(_) => return Err(FloodFileError::InvalidDestinationPath),
-        };
-
+    pub fn record(&mut self, path: &Path) -> Result<(), FloodFileError> {
+        self.recorder = Some(Recorder::create(path)?);
         Ok(())
     }
 
-    pub fn get_path(&self) -> PathBuf {
-        self.local_path.clone()
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    pub fn set_vlan(&mut self, vlan: Option<Vlan>) {
+        self.protocol.set_vlan(vlan);
+    }
+
+    pub fn vlan(&self) -> Option<Vlan> {
+        self.protocol.vlan()
     }
 }
@@ -0,0 +1,103 @@
+use crossbeam::channel::never;
+use futures_core::Stream;
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::errors::FloodFileError;
+use crate::network::payload::Payload;
+use crate::network::{send_backoff, Channel, Vlan, ETHERNET_PACKET_SIZE, MAX_SEND_RETRIES};
+use crate::protocol::Event;
+
+// a Channel driven by the tokio runtime: frames are awaited rather than polled,
+// and payloads are read off it as a Stream
+pub struct AsyncChannel {
+    channel: Channel,
+    frames: UnboundedReceiver<[u8; ETHERNET_PACKET_SIZE]>,
+}
+
+impl AsyncChannel {
+    pub fn new(interface: NetworkInterface) -> Result<Self, FloodFileError> {
+        Ok(Self::from(Channel::new(interface)?))
+    }
+
+    pub async fn send(&mut self, packet: Payload) -> Result<(), FloodFileError> {
+        for frame in self.channel.protocol.encode(&packet)? {
+            let mut retries = 0;
+            loop {
+                match self.channel.transmit(&frame) {
+                    Ok(()) => break,
+
+                    // backs off like the blocking path, without holding up the runtime
+                    Err(FloodFileError::SendBufferFull) if retries < MAX_SEND_RETRIES => {
+                        tokio::time::sleep(send_backoff(retries)).await;
+                        retries += 1;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            // large files are many frames, give other tasks a turn in between
+            tokio::task::yield_now().await;
+        }
+
+        Ok(())
+    }
+
+    pub async fn recv(&mut self) -> Option<Result<(MacAddr, Payload), FloodFileError>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    pub fn mac_addr(&self) -> MacAddr {
+        self.channel.mac_addr()
+    }
+
+    pub fn set_vlan(&mut self, vlan: Option<Vlan>) {
+        self.channel.set_vlan(vlan);
+    }
+}
+
+impl From<Channel> for AsyncChannel {
+    fn from(mut channel: Channel) -> Self {
+        // forward frames from the listener into something tokio can wake on. the
+        // channel can't be handed back without its frames, so there's no into_inner.
+        // dropping it stops the listener, which ends this thread too.
+        let buffer_rx = std::mem::replace(&mut channel.buffer_rx, never());
+        let (frames_tx, frames) = unbounded_channel();
+        thread::spawn(move || {
+            while let Ok(data) = buffer_rx.recv() {
+                if frames_tx.send(data).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { channel, frames }
+    }
+}
+
+impl Stream for AsyncChannel {
+    type Item = Result<(MacAddr, Payload), FloodFileError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let data = match self.frames.poll_recv(cx) {
+                Poll::Ready(Some(data)) => data,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            // partial chunks, progress and foreign frames aren't payloads yet
+            match self.channel.process(&data) {
+                Ok(Some(Event::Received(source, packet))) => {
+                    return Poll::Ready(Some(Ok((source, packet))))
+                }
+                Ok(_) => continue,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crossbeam::channel::unbounded;
+use pcap_file::pcap::PcapReader;
+use pcap_file::pcapng::blocks::enhanced_packet::EnhancedPacketBlock;
+use pcap_file::pcapng::blocks::interface_description::{
+    InterfaceDescriptionBlock, InterfaceDescriptionOption,
+};
+use pcap_file::pcapng::{Block, PcapNgReader, PcapNgWriter};
+use pcap_file::DataLink;
+use pnet::datalink::{DataLinkSender, NetworkInterface};
+use pnet::util::MacAddr;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::errors::FloodFileError;
+use crate::network::{Channel, ETHERNET_PACKET_SIZE};
+
+const PCAPNG_MAGIC: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+pub struct Recorder {
+    writer: PcapNgWriter<File>,
+}
+
+// discards everything sent while replaying a capture
+struct NullSender;
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self, FloodFileError> {
+        let mut writer = PcapNgWriter::new(File::create(path)?)?;
+
+        // enhanced packet timestamps are written in nanoseconds
+        writer.write_pcapng_block(InterfaceDescriptionBlock {
+            linktype: DataLink::ETHERNET,
+            snaplen: 0,
+            options: vec![InterfaceDescriptionOption::IfTsResol(9)],
+        })?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn write(&mut self, frame: &[u8]) -> Result<(), FloodFileError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        self.writer.write_pcapng_block(EnhancedPacketBlock {
+            interface_id: 0,
+            timestamp,
+            original_len: frame.len() as u32,
+            data: Cow::Borrowed(frame),
+            options: vec![],
+        })?;
+
+        Ok(())
+    }
+}
+
+// reads every ethernet frame from a pcap or pcapng capture
+pub fn read_frames(path: &Path) -> Result<Vec<Vec<u8>>, FloodFileError> {
+    let mut magic = [0u8; 4];
+    File::open(path)?.read_exact(&mut magic)?;
+
+    let mut frames = vec![];
+    if magic == PCAPNG_MAGIC {
+        let mut reader = PcapNgReader::new(File::open(path)?)?;
+        while let Some(block) = reader.next_block() {
+            match block? {
+                Block::EnhancedPacket(packet) => frames.push(packet.data.to_vec()),
+                Block::SimplePacket(packet) => frames.push(packet.data.to_vec()),
+                _ => (),
+            }
+        }
+    } else {
+        let mut reader = PcapReader::new(File::open(path)?)?;
+        while let Some(packet) = reader.next_packet() {
+            frames.push(packet?.data.to_vec());
+        }
+    }
+
+    Ok(frames)
+}
+
+// builds a channel whose received frames come from a capture instead of the wire
+pub fn replay(path: &Path) -> Result<Channel, FloodFileError> {
+    let (buffer_tx, buffer_rx) = unbounded::<[u8; ETHERNET_PACKET_SIZE]>();
+    for frame in read_frames(path)? {
+        let mut buffer = [0u8; ETHERNET_PACKET_SIZE];
+        let len = frame.len().min(ETHERNET_PACKET_SIZE);
+        buffer[..len].copy_from_slice(&frame[..len]);
+
+        buffer_tx.send(buffer).ok();
+    }
+
+    let interface = NetworkInterface {
+        name: path.display().to_string(),
+        description: String::from("capture replay"),
+        index: 0,
+        mac: Some(MacAddr::zero()),
+        ips: vec![],
+        flags: 0,
+    };
+
+    Ok(Channel::from_parts(
+        interface,
+        Box::new(NullSender),
+        buffer_rx,
+    ))
+}
+
+impl DataLinkSender for NullSender {
+    fn build_and_send(
+        &mut self,
+        _num_packets: usize,
+        _packet_size: usize,
+        _func: &mut dyn FnMut(&mut [u8]),
+    ) -> Option<io::Result<()>> {
+        Some(Ok(()))
+    }
+
+    fn send_to(
+        &mut self,
+        _packet: &[u8],
+        _dst: Option<NetworkInterface>,
+    ) -> Option<io::Result<()>> {
+        Some(Ok(()))
+    }
+}
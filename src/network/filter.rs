@@ -0,0 +1,162 @@
+use pnet::util::MacAddr;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::errors::FloodFileError;
+use crate::network::payload::Payload;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrafficKind {
+    Advertise,
+    Request,
+    File,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FilterRule {
+    pub rule: Rule,
+    pub kind: TrafficKind,
+    pub mac: MacAddr,
+}
+
+#[derive(Debug, Default)]
+pub struct Filters {
+    rules: HashSet<FilterRule>,
+}
+
+impl TrafficKind {
+    pub fn of(payload: &Payload) -> TrafficKind {
+        TrafficKind::of_opcode(payload.opcode())
+    }
+
+    // relayed chunks are forwarded before their payload is whole, so they're
+    // sorted by opcode alone
+    pub fn of_opcode(opcode: u8) -> TrafficKind {
+        match opcode {
+            Payload::ADVERTISE_OPCODE
+            | Payload::CATALOG_OPCODE
+            | Payload::UNSHARE_OPCODE
+            | Payload::CAROUSEL_OPCODE
+            | Payload::STREAM_OFFER_OPCODE => TrafficKind::Advertise,
+            Payload::DOWNLOAD_REQUEST_OPCODE
+            | Payload::CATALOG_REQUEST_OPCODE
+            | Payload::DISCOVER_OPCODE
+            | Payload::LOSS_REPORT_OPCODE
+            | Payload::STREAM_REQUEST_OPCODE => TrafficKind::Request,
+            // snippets are incoming content like files and pushes
+            Payload::FILE_OPCODE
+            | Payload::PUSH_OPCODE
+            | Payload::SNIPPET_OPCODE
+            | Payload::STREAM_DATA_OPCODE
+            | Payload::STREAM_END_OPCODE
+            | Payload::STREAM_REFUSED_OPCODE => TrafficKind::File,
+
+            // anything unknown is filtered like file data
+            _ => TrafficKind::File,
+        }
+    }
+}
+
+impl Filters {
+    pub fn permits(&self, kind: TrafficKind, mac: MacAddr) -> bool {
+        let rules: Vec<&FilterRule> = self.rules.iter().filter(|r| r.kind == kind).collect();
+
+        // deny always wins, and any allow rule turns the kind into an allow-list
+        if rules.iter().any(|r| r.rule == Rule::Deny && r.mac == mac) {
+            return false;
+        }
+
+        rules.iter().any(|r| r.rule == Rule::Allow && r.mac == mac)
+            || !rules.iter().any(|r| r.rule == Rule::Allow)
+    }
+
+    pub fn add(&mut self, rule: FilterRule) {
+        self.rules.insert(rule);
+    }
+
+    pub fn remove(&mut self, rule: &FilterRule) {
+        self.rules.remove(rule);
+    }
+
+    pub fn rules(&self) -> Vec<FilterRule> {
+        let mut rules: Vec<FilterRule> = self.rules.iter().copied().collect();
+        rules.sort_by_key(|r| r.to_string());
+        rules
+    }
+}
+
+impl fmt::Display for FilterRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = match self.rule {
+            Rule::Allow => "allow",
+            Rule::Deny => "deny",
+        };
+        let kind = match self.kind {
+            TrafficKind::Advertise => "advertise",
+            TrafficKind::Request => "request",
+            TrafficKind::File => "file",
+        };
+
+        write!(f, "{0} {1} {2}", rule, kind, self.mac)
+    }
+}
+
+// format: "<allow|deny> <advertise|request|file> <mac>"
+impl FromStr for FilterRule {
+    type Err = FloodFileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let [rule, kind, mac] = parts[..] else {
+            return Err(FloodFileError::InvalidFilterRule);
+        };
+
+        let rule = match rule {
+            "allow" => Rule::Allow,
+            "deny" => Rule::Deny,
+            _ => return Err(FloodFileError::InvalidFilterRule),
+        };
+        let kind = match kind {
+            "advertise" => TrafficKind::Advertise,
+            "request" => TrafficKind::Request,
+            "file" => TrafficKind::File,
+            _ => return Err(FloodFileError::InvalidFilterRule),
+        };
+        let mac = MacAddr::from_str(mac).map_err(|_| FloodFileError::InvalidFilterRule)?;
+
+        Ok(FilterRule { rule, kind, mac })
+    }
+}
+
+// one rule per line, written the way they're typed in
+pub fn load_rules(path: &Path) -> Result<Vec<FilterRule>, FloodFileError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(_) => return Err(FloodFileError::FilterFileError),
+    };
+
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(FilterRule::from_str)
+        .collect()
+}
+
+pub fn save_rules(path: &Path, rules: &[FilterRule]) -> Result<(), FloodFileError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|_| FloodFileError::FilterFileError)?;
+    }
+
+    let text: String = rules.iter().map(|r| format!("{0}\n", r)).collect();
+    fs::write(path, text).map_err(|_| FloodFileError::FilterFileError)
+}
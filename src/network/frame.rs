@@ -0,0 +1,160 @@
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::packet::vlan::VlanPacket;
+use pnet::packet::Packet;
+use pnet::util::MacAddr;
+
+use crate::errors::FloodFileError;
+use crate::network::{Key, Nonce, Vlan};
+
+// wire layout of a single floodfile frame:
+//
+//   ethernet: [dst (6)] [src (6)] ([0x8100 (2)] [tci (2)])? [0x0806 (2)]
+//   arp:      [htype (2)] [ptype (2)] [hlen (1)] [plen (1)] [oper (2)]
+//             [sender mac (6)] [floodfile data (plen)]
+//             [target mac (6)] [floodfile data (plen)]
+//   floodfile data:
+//             [preamble "flod" (4)] [version (1)] [opcode (1)] [hops (1)]
+//             [offset (2)] [total (2)] [key (8)] [nonce (4)] [chunk (plen - 23)]
+//
+// the arp protocol length field is a single byte, so each chunk is capped at
+// 255 bytes minus the floodfile header. every integer is little endian.
+//
+// the arp sender mac is always the host that built the payload. relays keep it
+// and send from their own ethernet address, bumping hops as they go.
+//
+// 1.0.0 used a "file" preamble with no version, and only the preamble tells it
+// whether a frame is floodfile. the new preamble keeps it from misreading these
+// frames and keeps us from misreading its. later versions bump the version.
+pub const ETHERNET_HEADER_SIZE: usize = 14;
+pub const VLAN_TAG_SIZE: usize = 4;
+pub const ARP_HEADER_SIZE: usize = 8;
+pub const MSG_PREAMBLE: &[u8] = b"flod";
+pub const PROTOCOL_VERSION: u8 = 2;
+pub const FLOODFILE_HEADER_SIZE: usize = MSG_PREAMBLE.len() + 7 + 8 + 4;
+pub const CHUNK_SIZE: usize = u8::MAX as usize - FLOODFILE_HEADER_SIZE;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub source: MacAddr,
+    pub relayed_by: Option<MacAddr>,
+    pub vlan: Option<Vlan>,
+    pub opcode: u8,
+    pub hops: u8,
+    pub offset: u16,
+    pub total: u16,
+    pub key: Key,
+    pub nonce: Nonce,
+    pub chunk: Vec<u8>,
+}
+
+impl Frame {
+    pub fn encode(&self) -> Result<Vec<u8>, FloodFileError> {
+        let data = [
+            MSG_PREAMBLE,
+            &[PROTOCOL_VERSION, self.opcode, self.hops],
+            &self.offset.to_le_bytes()[..],
+            &self.total.to_le_bytes()[..],
+            &self.key[..],
+            &self.nonce[..],
+            &self.chunk,
+        ]
+        .concat();
+
+        if data.len() > u8::MAX as usize {
+            return Err(FloodFileError::PacketTooLarge);
+        }
+
+        let arp_packet = [
+            &[0, 1],               // hardware type
+            &[8, 0],               // protocol type
+            &[6][..],              // hardware size
+            &[data.len() as u8],   // payload length
+            &[0, 1],               // opcode - req
+            &self.source.octets(), // sender mac
+            &data,                 // payload!
+            &[0; 6],               // target mac
+            &data,                 // payload!
+        ]
+        .concat();
+
+        // 802.1Q: [priority (3 bits), dei (1 bit), vlan id (12 bits)] + [inner ethertype]
+        let (ethertype, payload) = match self.vlan {
+            Some(vlan) => {
+                let tci = ((vlan.priority as u16) << 13) | (vlan.id & 0x0fff);
+                let tag = [&tci.to_be_bytes()[..], &EtherTypes::Arp.0.to_be_bytes()[..]].concat();
+                (EtherTypes::Vlan, [&tag[..], &arp_packet[..]].concat())
+            }
+            None => (EtherTypes::Arp, arp_packet),
+        };
+
+        let mut ethernet_buffer = vec![0; ETHERNET_HEADER_SIZE + payload.len()];
+        let mut ethernet_packet = MutableEthernetPacket::new(&mut ethernet_buffer)
+            .ok_or(FloodFileError::FailedToSerializeArp)?;
+        ethernet_packet.set_source(self.relayed_by.unwrap_or(self.source));
+        ethernet_packet.set_destination(MacAddr::broadcast());
+        ethernet_packet.set_ethertype(ethertype);
+        ethernet_packet.set_payload(&payload);
+
+        Ok(ethernet_buffer)
+    }
+
+    // parses a floodfile frame, returning it along with its length on the wire
+    pub fn decode(buffer: &[u8]) -> Option<(Frame, usize)> {
+        let packet = EthernetPacket::new(buffer)?;
+
+        let (vlan, arp) = match packet.get_ethertype() {
+            EtherTypes::Arp => (None, packet.payload()),
+            EtherTypes::Vlan => {
+                let tagged = VlanPacket::new(packet.payload())?;
+                if tagged.get_ethertype() != EtherTypes::Arp {
+                    return None;
+                }
+
+                let vlan = Vlan {
+                    id: tagged.get_vlan_identifier(),
+                    priority: tagged.get_priority_code_point().0,
+                };
+                (Some(vlan), packet.payload().get(VLAN_TAG_SIZE..)?)
+            }
+            _ => return None,
+        };
+
+        let data_len = *arp.get(5)? as usize;
+        let frame_len = buffer.len() - arp.len() + ARP_HEADER_SIZE + 2 * (6 + data_len);
+        if arp.get(7) != Some(&1) || data_len < FLOODFILE_HEADER_SIZE || frame_len > buffer.len() {
+            return None;
+        }
+
+        let data = &arp[ARP_HEADER_SIZE + 6..ARP_HEADER_SIZE + 6 + data_len];
+        // anything from another version is laid out differently past the preamble
+        if &data[0..4] != MSG_PREAMBLE || data[4] != PROTOCOL_VERSION {
+            return None;
+        }
+
+        let sender: [u8; 6] = arp[ARP_HEADER_SIZE..ARP_HEADER_SIZE + 6].try_into().ok()?;
+        let source = MacAddr::from(sender);
+        let relayed_by = match packet.get_source() {
+            mac if mac == source => None,
+            mac => Some(mac),
+        };
+
+        let frame = Frame {
+            source,
+            relayed_by,
+            vlan,
+            opcode: data[5],
+            hops: data[6],
+            offset: u16::from_le_bytes([data[7], data[8]]),
+            total: u16::from_le_bytes([data[9], data[10]]),
+            key: data[11..19].try_into().ok()?,
+            nonce: data[19..23].try_into().ok()?,
+            chunk: data[FLOODFILE_HEADER_SIZE..].to_vec(),
+        };
+
+        if frame.offset >= frame.total {
+            return None;
+        }
+
+        Some((frame, frame_len))
+    }
+}
@@ -0,0 +1,239 @@
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use pnet::datalink::NetworkInterface;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::network::utils::link_up;
+
+// how often the fallback re-reads the interface list, and how often the
+// netlink watcher wakes up to check whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const WAKE_INTERVAL: Duration = Duration::from_millis(200);
+
+// watches for interfaces appearing, disappearing or changing link state.
+// changes only say that something happened, re-read the interfaces to see what.
+pub struct InterfaceMonitor {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    changes: Receiver<()>,
+}
+
+impl InterfaceMonitor {
+    pub fn start() -> Self {
+        let (changes_tx, changes) = unbounded::<()>();
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = thread::spawn({
+            let running = running.clone();
+            move || watch(changes_tx, running)
+        });
+
+        Self {
+            running,
+            handle: Some(handle),
+            changes,
+        }
+    }
+
+    pub fn changes(&self) -> Receiver<()> {
+        self.changes.clone()
+    }
+}
+
+// what a fresh look at the interfaces means for the links we're on
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkChange {
+    Pause(usize),
+    Resume(usize, NetworkInterface),
+    FailOver(NetworkInterface),
+}
+
+// links are (interface name, paused) in order, interfaces as usable_interfaces
+// lists them. a link is only up while its interface is listed and running.
+pub fn link_changes(links: &[(String, bool)], interfaces: &[NetworkInterface]) -> Vec<LinkChange> {
+    let mut changes = vec![];
+    let mut all_paused = true;
+
+    for (via, (name, paused)) in links.iter().enumerate() {
+        let live = interfaces.iter().find(|i| i.name == *name && link_up(i));
+
+        match (live, paused) {
+            (Some(interface), true) => changes.push(LinkChange::Resume(via, interface.clone())),
+            (None, false) => changes.push(LinkChange::Pause(via)),
+            _ => (),
+        }
+        all_paused &= live.is_none();
+    }
+
+    // rather carry on elsewhere than wait for every link to return
+    if all_paused {
+        let spare = interfaces
+            .iter()
+            .find(|i| link_up(i) && !links.iter().any(|(name, _)| *name == i.name));
+
+        if let Some(interface) = spare {
+            changes.push(LinkChange::FailOver(interface.clone()));
+        }
+    }
+
+    changes
+}
+
+impl Drop for InterfaceMonitor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+fn watch(changes: Sender<()>, running: Arc<AtomicBool>) {
+    #[cfg(target_os = "linux")]
+    match netlink::open() {
+        Ok(socket) => return netlink::watch(socket, changes, running),
+        Err(e) => log::warn!("netlink unavailable, polling interfaces instead: {e}"),
+    }
+
+    poll(changes, running);
+}
+
+// name, flags and addresses are everything usable_interfaces and link_up look at
+fn snapshot() -> Vec<(String, bool, usize)> {
+    let mut interfaces: Vec<_> = pnet::datalink::interfaces()
+        .iter()
+        .map(|i| (i.name.clone(), link_up(i), i.ips.len()))
+        .collect();
+
+    interfaces.sort();
+    interfaces
+}
+
+fn poll(changes: Sender<()>, running: Arc<AtomicBool>) {
+    let mut last = snapshot();
+    let mut waited = Duration::ZERO;
+    while running.load(Ordering::Relaxed) {
+        thread::sleep(WAKE_INTERVAL);
+        waited += WAKE_INTERVAL;
+        if waited < POLL_INTERVAL {
+            continue;
+        }
+
+        waited = Duration::ZERO;
+        let current = snapshot();
+        if current != last && changes.send(()).is_err() {
+            return;
+        }
+
+        last = current;
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod netlink {
+    use crossbeam::channel::Sender;
+    use std::io;
+    use std::mem;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::WAKE_INTERVAL;
+
+    pub fn open() -> io::Result<OwnedFd> {
+        // SAFETY: plain socket(2) call, the fd is owned straight away
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd was just checked to be a valid descriptor, and nothing else
+        // holds it, so the OwnedFd is its only owner
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // link up/down, interfaces coming and going, and addresses changing.
+        // SAFETY: sockaddr_nl is plain integers, all zero is a valid value
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups =
+            (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+
+        // SAFETY: addr is a valid sockaddr_nl for the length given
+        let bound = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // wake up regularly so the monitor can be stopped
+        let timeout = libc::timeval {
+            tv_sec: 0,
+            tv_usec: WAKE_INTERVAL.as_micros() as libc::suseconds_t,
+        };
+
+        // SAFETY: timeout is a valid timeval for the length given
+        let set = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if set < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(socket)
+    }
+
+    pub fn watch(socket: OwnedFd, changes: Sender<()>, running: Arc<AtomicBool>) {
+        let mut buffer = [0u8; 8192];
+        while running.load(Ordering::Relaxed) {
+            // SAFETY: buffer is valid for writes of its full length
+            let len = unsafe {
+                libc::recv(
+                    socket.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+
+            if len < 0 {
+                let e = io::Error::last_os_error();
+                match e.kind() {
+                    io::ErrorKind::WouldBlock
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted => continue,
+                    _ => {
+                        // overruns drop messages, but we only need to know something changed
+                        if e.raw_os_error() == Some(libc::ENOBUFS) {
+                            changes.send(()).ok();
+                            continue;
+                        }
+
+                        log::warn!("netlink read failed, polling interfaces instead: {e}");
+                        return super::poll(changes, running);
+                    }
+                }
+            }
+
+            if changes.send(()).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+// frames per second
+const INITIAL_RATE: f64 = 2000.0;
+const MIN_RATE: f64 = 50.0;
+const MAX_RATE: f64 = 50_000.0;
+const RATE_INCREASE: f64 = 100.0;
+
+// loss above this in a report halves the rate
+const LOSS_THRESHOLD: f64 = 0.01;
+
+// reports from several receivers about the same burst only count once
+const DECREASE_HOLDOFF: Duration = Duration::from_secs(1);
+
+// how far behind schedule we may catch up in one burst after a late wakeup
+const MAX_LAG: Duration = Duration::from_millis(5);
+
+// spaces frames out at a rate that grows additively while receivers report no
+// loss and halves when they do
+pub struct Pacer {
+    rate: f64,
+    next: Instant,
+    decreased: Option<Instant>,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self {
+            rate: INITIAL_RATE,
+            next: Instant::now(),
+            decreased: None,
+        }
+    }
+}
+
+impl Pacer {
+    pub fn rate(&self) -> u32 {
+        self.rate as u32
+    }
+
+    pub fn next(&self) -> Instant {
+        self.next
+    }
+
+    pub fn ready(&self, now: Instant) -> bool {
+        now >= self.next
+    }
+
+    pub fn sent(&mut self, now: Instant) {
+        let interval = Duration::from_secs_f64(1.0 / self.rate);
+        self.next = self.next.max(now - MAX_LAG) + interval;
+    }
+
+    pub fn report(&mut self, received: u16, expected: u16, now: Instant) {
+        if expected == 0 {
+            return;
+        }
+
+        let loss = 1.0 - f64::from(received.min(expected)) / f64::from(expected);
+        if loss <= LOSS_THRESHOLD {
+            self.rate = (self.rate + RATE_INCREASE).min(MAX_RATE);
+            return;
+        }
+
+        if self
+            .decreased
+            .is_some_and(|at| now.duration_since(at) < DECREASE_HOLDOFF)
+        {
+            return;
+        }
+
+        self.rate = (self.rate / 2.0).max(MIN_RATE);
+        self.decreased = Some(now);
+    }
+}
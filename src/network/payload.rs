@@ -1,20 +1,101 @@
 use lz4_flex::block::{compress_prepend_size, decompress_size_prepended};
+use pnet::util::MacAddr;
 
-use crate::network::FileHash;
+use crate::network::{FileHash, Key, StreamId};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
 pub enum Payload {
     File(FileHash, Vec<u8>),
     Advertise(String),
     DownloadRequest(FileHash),
+    Discover,
+    CatalogRequest(MacAddr),
+    Catalog(u16, u16, Vec<CatalogEntry>),
+    Unshare(FileHash),
+    // chunks received out of those expected for a transfer since the last report
+    LossReport(Key, u16, u16),
+    // an advertisement for a file whose chunks are being looped
+    Carousel(String),
+    // a file sent unasked, under its name
+    Push(String, Vec<u8>),
+    Snippet(String),
+    // streams of unknown length go out as numbered segments once someone asks
+    // for them, and missed segments are asked for again by number
+    StreamOffer(StreamId),
+    StreamRequest(StreamId, u32),
+    StreamData(StreamId, u32, Vec<u8>),
+    StreamEnd(StreamId, u32),
+    // a receiver that asked for segments the sender no longer keeps
+    StreamRefused(StreamId, MacAddr),
 }
 
 impl Payload {
+    pub const FILE_OPCODE: u8 = 0;
+    pub const ADVERTISE_OPCODE: u8 = 1;
+    pub const DOWNLOAD_REQUEST_OPCODE: u8 = 2;
+    pub const DISCOVER_OPCODE: u8 = 3;
+    pub const CATALOG_REQUEST_OPCODE: u8 = 4;
+    pub const CATALOG_OPCODE: u8 = 5;
+    pub const UNSHARE_OPCODE: u8 = 6;
+    pub const LOSS_REPORT_OPCODE: u8 = 7;
+    pub const CAROUSEL_OPCODE: u8 = 8;
+    pub const PUSH_OPCODE: u8 = 9;
+    pub const SNIPPET_OPCODE: u8 = 10;
+    pub const STREAM_OFFER_OPCODE: u8 = 11;
+    pub const STREAM_REQUEST_OPCODE: u8 = 12;
+    pub const STREAM_DATA_OPCODE: u8 = 13;
+    pub const STREAM_END_OPCODE: u8 = 14;
+    pub const STREAM_REFUSED_OPCODE: u8 = 15;
+
+    // snippets are meant for a command line or a stack trace, not a file
+    pub const MAX_SNIPPET_LEN: usize = 4096;
+
     pub fn opcode(&self) -> u8 {
         match self {
-            Payload::File(_, _) => 0,
-            Payload::Advertise(_) => 1,
-            Payload::DownloadRequest(_) => 2,
+            Payload::File(_, _) => Payload::FILE_OPCODE,
+            Payload::Advertise(_) => Payload::ADVERTISE_OPCODE,
+            Payload::DownloadRequest(_) => Payload::DOWNLOAD_REQUEST_OPCODE,
+            Payload::Discover => Payload::DISCOVER_OPCODE,
+            Payload::CatalogRequest(_) => Payload::CATALOG_REQUEST_OPCODE,
+            Payload::Catalog(_, _, _) => Payload::CATALOG_OPCODE,
+            Payload::Unshare(_) => Payload::UNSHARE_OPCODE,
+            Payload::LossReport(_, _, _) => Payload::LOSS_REPORT_OPCODE,
+            Payload::Carousel(_) => Payload::CAROUSEL_OPCODE,
+            Payload::Push(_, _) => Payload::PUSH_OPCODE,
+            Payload::Snippet(_) => Payload::SNIPPET_OPCODE,
+            Payload::StreamOffer(_) => Payload::STREAM_OFFER_OPCODE,
+            Payload::StreamRequest(_, _) => Payload::STREAM_REQUEST_OPCODE,
+            Payload::StreamData(_, _, _) => Payload::STREAM_DATA_OPCODE,
+            Payload::StreamEnd(_, _) => Payload::STREAM_END_OPCODE,
+            Payload::StreamRefused(_, _) => Payload::STREAM_REFUSED_OPCODE,
+        }
+    }
+
+    pub fn kind(opcode: u8) -> &'static str {
+        match opcode {
+            Payload::FILE_OPCODE => "file",
+            Payload::ADVERTISE_OPCODE => "advertise",
+            Payload::DOWNLOAD_REQUEST_OPCODE => "download-request",
+            Payload::DISCOVER_OPCODE => "discover",
+            Payload::CATALOG_REQUEST_OPCODE => "catalog-request",
+            Payload::CATALOG_OPCODE => "catalog",
+            Payload::UNSHARE_OPCODE => "unshare",
+            Payload::LOSS_REPORT_OPCODE => "loss-report",
+            Payload::CAROUSEL_OPCODE => "carousel",
+            Payload::PUSH_OPCODE => "push",
+            Payload::SNIPPET_OPCODE => "snippet",
+            Payload::STREAM_OFFER_OPCODE => "stream-offer",
+            Payload::STREAM_REQUEST_OPCODE => "stream-request",
+            Payload::STREAM_DATA_OPCODE => "stream-data",
+            Payload::STREAM_END_OPCODE => "stream-end",
+            Payload::STREAM_REFUSED_OPCODE => "stream-refused",
+            _ => "unknown",
         }
     }
 
@@ -24,14 +105,49 @@ impl Payload {
                 let data = compress_prepend_size(data);
                 [&filehash[..], &data[..]].concat()
             }
-            Payload::Advertise(path) => path.as_bytes().to_vec(),
+            Payload::Advertise(path) | Payload::Carousel(path) | Payload::Snippet(path) => {
+                path.as_bytes().to_vec()
+            }
             Payload::DownloadRequest(filehash) => filehash.to_vec(),
+            // empty payloads produce no chunks, so pad with a single byte
+            Payload::Discover => vec![0],
+            Payload::CatalogRequest(target) => target.octets().to_vec(),
+            Payload::Catalog(page, pages, entries) => {
+                // structure: [page (2), pages (2)] + [[size (8), name len (2), name]..]
+                let mut data = [page.to_le_bytes(), pages.to_le_bytes()].concat();
+                for entry in entries {
+                    let name = entry.name.as_bytes();
+                    data.extend_from_slice(&entry.size.to_le_bytes());
+                    data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                    data.extend_from_slice(name);
+                }
+                data
+            }
+            Payload::Unshare(filehash) => filehash.to_vec(),
+            Payload::Push(name, data) => {
+                // structure: [name len (2), name] + compressed file
+                let name = name.as_bytes();
+                let data = compress_prepend_size(data);
+                [&(name.len() as u16).to_le_bytes()[..], name, &data[..]].concat()
+            }
+            Payload::StreamOffer(id) => id.to_vec(),
+            Payload::StreamRequest(id, seq) | Payload::StreamEnd(id, seq) => {
+                [&id[..], &seq.to_le_bytes()].concat()
+            }
+            Payload::StreamRefused(id, receiver) => [&id[..], &receiver.octets()].concat(),
+            Payload::StreamData(id, seq, data) => {
+                let data = compress_prepend_size(data);
+                [&id[..], &seq.to_le_bytes(), &data[..]].concat()
+            }
+            Payload::LossReport(key, received, expected) => {
+                [&key[..], &received.to_le_bytes(), &expected.to_le_bytes()].concat()
+            }
         }
     }
 
     pub fn deserialize(opcode: u8, data: &[u8]) -> Option<Payload> {
         match opcode {
-            0 => {
+            Payload::FILE_OPCODE => {
                 // need at least 16 bytes for hash
                 if data.len() < 16 {
                     return None;
@@ -46,11 +162,11 @@ impl Payload {
 
                 Some(Payload::File(hash, file))
             }
-            1 => {
+            Payload::ADVERTISE_OPCODE => {
                 let path = std::str::from_utf8(data).ok()?.to_string();
                 Some(Payload::Advertise(path))
             }
-            2 => {
+            Payload::DOWNLOAD_REQUEST_OPCODE => {
                 // need exactly 16 bytes for hash
                 if data.len() < 16 {
                     return None;
@@ -59,6 +175,82 @@ impl Payload {
                 let hash: FileHash = data[0..16].try_into().ok()?;
                 Some(Payload::DownloadRequest(hash))
             }
+            Payload::DISCOVER_OPCODE => Some(Payload::Discover),
+            Payload::CATALOG_REQUEST_OPCODE => {
+                let mac: [u8; 6] = data.get(0..6)?.try_into().ok()?;
+                Some(Payload::CatalogRequest(MacAddr::from(mac)))
+            }
+            Payload::CATALOG_OPCODE => {
+                let page = u16::from_le_bytes(data.get(0..2)?.try_into().ok()?);
+                let pages = u16::from_le_bytes(data.get(2..4)?.try_into().ok()?);
+
+                let mut entries = vec![];
+                let mut rest = &data[4..];
+                while !rest.is_empty() {
+                    let size = u64::from_le_bytes(rest.get(0..8)?.try_into().ok()?);
+                    let len = u16::from_le_bytes(rest.get(8..10)?.try_into().ok()?) as usize;
+                    let name = std::str::from_utf8(rest.get(10..10 + len)?).ok()?;
+
+                    entries.push(CatalogEntry {
+                        name: name.to_string(),
+                        size,
+                    });
+                    rest = &rest[10 + len..];
+                }
+
+                Some(Payload::Catalog(page, pages, entries))
+            }
+            Payload::UNSHARE_OPCODE => {
+                let hash: FileHash = data.get(0..16)?.try_into().ok()?;
+                Some(Payload::Unshare(hash))
+            }
+            Payload::LOSS_REPORT_OPCODE => {
+                let key: Key = data.get(0..8)?.try_into().ok()?;
+                let received = u16::from_le_bytes(data.get(8..10)?.try_into().ok()?);
+                let expected = u16::from_le_bytes(data.get(10..12)?.try_into().ok()?);
+                Some(Payload::LossReport(key, received, expected))
+            }
+            Payload::CAROUSEL_OPCODE => {
+                let path = std::str::from_utf8(data).ok()?.to_string();
+                Some(Payload::Carousel(path))
+            }
+            Payload::PUSH_OPCODE => {
+                let len = u16::from_le_bytes(data.get(0..2)?.try_into().ok()?) as usize;
+                let name = std::str::from_utf8(data.get(2..2 + len)?).ok()?.to_string();
+                let file = decompress_size_prepended(&data[2 + len..]).ok()?;
+                Some(Payload::Push(name, file))
+            }
+            Payload::SNIPPET_OPCODE => {
+                if data.len() > Payload::MAX_SNIPPET_LEN {
+                    return None;
+                }
+
+                let text = std::str::from_utf8(data).ok()?.to_string();
+                Some(Payload::Snippet(text))
+            }
+            Payload::STREAM_OFFER_OPCODE => {
+                let id: StreamId = data.get(0..16)?.try_into().ok()?;
+                Some(Payload::StreamOffer(id))
+            }
+            Payload::STREAM_REQUEST_OPCODE | Payload::STREAM_END_OPCODE => {
+                let id: StreamId = data.get(0..16)?.try_into().ok()?;
+                let seq = u32::from_le_bytes(data.get(16..20)?.try_into().ok()?);
+                match opcode {
+                    Payload::STREAM_REQUEST_OPCODE => Some(Payload::StreamRequest(id, seq)),
+                    _ => Some(Payload::StreamEnd(id, seq)),
+                }
+            }
+            Payload::STREAM_DATA_OPCODE => {
+                let id: StreamId = data.get(0..16)?.try_into().ok()?;
+                let seq = u32::from_le_bytes(data.get(16..20)?.try_into().ok()?);
+                let segment = decompress_size_prepended(data.get(20..)?).ok()?;
+                Some(Payload::StreamData(id, seq, segment))
+            }
+            Payload::STREAM_REFUSED_OPCODE => {
+                let id: StreamId = data.get(0..16)?.try_into().ok()?;
+                let mac: [u8; 6] = data.get(16..22)?.try_into().ok()?;
+                Some(Payload::StreamRefused(id, MacAddr::from(mac)))
+            }
             _ => None,
         }
     }
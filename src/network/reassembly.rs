@@ -0,0 +1,202 @@
+use pnet::util::MacAddr;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::errors::FloodFileError;
+use crate::network::frame::Frame;
+use crate::network::Key;
+
+// partial packets nothing has arrived for in this long are dropped
+pub const REASSEMBLY_TTL: Duration = Duration::from_secs(30);
+
+// unfinished packets from one sender, and chunk bytes held for everyone
+const MAX_PER_PEER: usize = 64;
+const MAX_BUFFERED: usize = 64 * 1024 * 1024;
+
+pub enum Progress {
+    Partial { received: usize, total: usize },
+    Complete { opcode: u8, data: Vec<u8> },
+}
+
+struct Reassembly {
+    opcode: u8,
+    chunks: Vec<Vec<u8>>,
+    // the range seen so far starts at first and runs for expected chunks, wrapping
+    // past the end for carousels. gaps inside it are lost, anything outside it
+    // was sent before we joined or hasn't been sent yet
+    first: usize,
+    received: usize,
+    expected: usize,
+    reported: (usize, usize),
+    bytes: usize,
+    updated: Instant,
+}
+
+pub struct LossReport {
+    pub source: MacAddr,
+    pub key: Key,
+    pub received: u16,
+    pub expected: u16,
+}
+
+#[derive(Default)]
+pub struct Reassembler {
+    packets: HashMap<(MacAddr, Key), Reassembly>,
+    buffered: usize,
+}
+
+impl Reassembly {
+    // grows the range seen in whichever direction reaches offset sooner, so a
+    // chunk reordered just before the first one doesn't read as a wrap
+    fn observe(&mut self, offset: usize) {
+        let total = self.chunks.len();
+        let position = (offset + total - self.first) % total;
+        if position < self.expected {
+            return;
+        }
+
+        let ahead = position + 1 - self.expected;
+        let behind = total - position;
+        if behind < ahead {
+            self.first = offset;
+            self.expected += behind;
+        } else {
+            self.expected += ahead;
+        }
+    }
+}
+
+impl Reassembler {
+    pub fn opcode(&self, source: MacAddr, key: Key) -> Option<u8> {
+        self.packets.get(&(source, key)).map(|p| p.opcode)
+    }
+
+    pub fn first_chunk(&self, source: MacAddr, key: Key) -> Option<&[u8]> {
+        let packet = self.packets.get(&(source, key))?;
+        packet
+            .chunks
+            .first()
+            .filter(|c| !c.is_empty())
+            .map(|c| &c[..])
+    }
+
+    // what arrived of each unfinished packet since the last call
+    pub fn loss_reports(&mut self) -> Vec<LossReport> {
+        let mut reports = vec![];
+        for ((source, key), packet) in self.packets.iter_mut() {
+            let received = packet.received - packet.reported.0;
+            let expected = packet.expected - packet.reported.1;
+            if expected == 0 {
+                continue;
+            }
+
+            packet.reported = (packet.received, packet.expected);
+            reports.push(LossReport {
+                source: *source,
+                key: *key,
+                received: received as u16,
+                expected: expected as u16,
+            });
+        }
+
+        reports
+    }
+
+    // drops partial packets that stopped arriving, returning how many
+    pub fn prune(&mut self, now: Instant) -> usize {
+        let before = self.packets.len();
+        self.packets
+            .retain(|_, p| now.duration_since(p.updated) < REASSEMBLY_TTL);
+        self.buffered = self.packets.values().map(|p| p.bytes).sum();
+        before - self.packets.len()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn push(&mut self, frame: Frame) -> Result<Progress, FloodFileError> {
+        let (source, key, total) = (frame.source, frame.key, frame.total as usize);
+        if frame.offset as usize >= total {
+            return Err(FloodFileError::ReassemblyMismatch);
+        }
+
+        if !self.packets.contains_key(&(source, key)) {
+            self.make_room(source);
+
+            // the empty chunk slots count too, a large total costs memory upfront
+            self.buffered += total * std::mem::size_of::<Vec<u8>>();
+        }
+
+        // allocate vec with total size, scoped to the sender so keys can't be spliced
+        let packet = self.packets.entry((source, key)).or_insert(Reassembly {
+            opcode: frame.opcode,
+            chunks: vec![vec![]; total],
+            first: frame.offset as usize,
+            received: 0,
+            expected: 0,
+            reported: (0, 0),
+            bytes: total * std::mem::size_of::<Vec<u8>>(),
+            updated: Instant::now(),
+        });
+
+        if packet.opcode != frame.opcode || packet.chunks.len() != total {
+            log::warn!(
+                "rejected chunk from {0}: key {1:?} reused with different opcode or total",
+                source,
+                key
+            );
+            return Err(FloodFileError::ReassemblyMismatch);
+        }
+
+        // store portion if we haven't already
+        let offset = frame.offset as usize;
+        if packet.chunks[offset].is_empty() {
+            packet.bytes += frame.chunk.len();
+            self.buffered += frame.chunk.len();
+            packet.chunks[offset] = frame.chunk;
+            packet.received += 1;
+        }
+        packet.observe(offset);
+        packet.updated = Instant::now();
+
+        let received = packet.received;
+        if received < total {
+            return Ok(Progress::Partial { received, total });
+        }
+
+        let packet = self.packets.remove(&(source, key));
+        self.buffered -= packet.as_ref().map_or(0, |p| p.bytes);
+        Ok(Progress::Complete {
+            opcode: frame.opcode,
+            data: packet.map(|p| p.chunks.concat()).unwrap_or_default(),
+        })
+    }
+
+    // a sender gets a limited number of packets on the go, and everyone shares
+    // a limit on buffered chunks. the stalest packets make way for new ones.
+    fn make_room(&mut self, source: MacAddr) {
+        loop {
+            let from_source = self.packets.keys().filter(|(s, _)| *s == source).count();
+            let stalest = match (from_source >= MAX_PER_PEER, self.buffered >= MAX_BUFFERED) {
+                (true, _) => self
+                    .packets
+                    .iter()
+                    .filter(|((s, _), _)| *s == source)
+                    .min_by_key(|(_, p)| p.updated),
+                (false, true) => self.packets.iter().min_by_key(|(_, p)| p.updated),
+                (false, false) => return,
+            };
+
+            let id = match stalest {
+                Some((id, _)) => *id,
+                None => return,
+            };
+
+            log::warn!("dropped unfinished packet from {0}: key {1:?}", id.0, id.1);
+            if let Some(packet) = self.packets.remove(&id) {
+                self.buffered -= packet.bytes;
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use pnet::util::MacAddr;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::errors::FloodFileError;
+use crate::network::frame::Frame;
+use crate::network::{Key, Nonce};
+
+const DEFAULT_MAX_HOPS: u8 = 4;
+const DEFAULT_RATE: u32 = 200;
+
+// how long a forwarded chunk is remembered, longer than any loop takes to close
+const SEEN_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayConfig {
+    pub max_hops: u8,
+    // frames per second forwarded for any one source
+    pub rate: u32,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            max_hops: DEFAULT_MAX_HOPS,
+            rate: DEFAULT_RATE,
+        }
+    }
+}
+
+// format: "<max hops>" or "<max hops>:<frames per second>"
+impl FromStr for RelayConfig {
+    type Err = FloodFileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hops, rate) = match s.trim().split_once(':') {
+            Some((hops, rate)) => (hops, Some(rate)),
+            None => (s.trim(), None),
+        };
+
+        let max_hops = hops
+            .parse::<u8>()
+            .map_err(|_| FloodFileError::InvalidRelay)?;
+        let rate = match rate {
+            Some(rate) => rate
+                .parse::<u32>()
+                .map_err(|_| FloodFileError::InvalidRelay)?,
+            None => DEFAULT_RATE,
+        };
+
+        if max_hops == 0 || rate == 0 {
+            return Err(FloodFileError::InvalidRelay);
+        }
+
+        Ok(RelayConfig { max_hops, rate })
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// decides which frames cross between segments: each chunk once, within the hop
+// limit, and no faster than the rate limit allows for its source
+pub struct Relay {
+    config: RelayConfig,
+    seen: HashMap<(MacAddr, Nonce, Key, u16), Instant>,
+    buckets: HashMap<MacAddr, Bucket>,
+    pruned: Instant,
+}
+
+impl Relay {
+    pub fn new(config: RelayConfig) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+            buckets: HashMap::new(),
+            pruned: Instant::now(),
+        }
+    }
+
+    pub fn config(&self) -> RelayConfig {
+        self.config
+    }
+
+    // the copy to send on the other segments, if any
+    pub fn forward(&mut self, frame: &Frame, now: Instant) -> Option<Frame> {
+        self.prune(now);
+
+        if frame.hops >= self.config.max_hops {
+            return None;
+        }
+
+        // a chunk we've already passed on has looped back, or come in twice
+        let id = (frame.source, frame.nonce, frame.key, frame.offset);
+        if self.seen.contains_key(&id) {
+            return None;
+        }
+
+        if !self.take_token(frame.source, now) {
+            log::warn!("relay rate limit hit for {0}", frame.source);
+            return None;
+        }
+
+        self.seen.insert(id, now);
+        Some(Frame {
+            hops: frame.hops + 1,
+            ..frame.clone()
+        })
+    }
+
+    fn take_token(&mut self, source: MacAddr, now: Instant) -> bool {
+        // a second's worth of burst, refilled at the configured rate
+        let rate = self.config.rate as f64;
+        let bucket = self.buckets.entry(source).or_insert(Bucket {
+            tokens: rate,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    fn prune(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.pruned) < SEEN_TTL {
+            return;
+        }
+
+        self.seen
+            .retain(|_, at| now.saturating_duration_since(*at) < SEEN_TTL);
+        self.buckets
+            .retain(|_, b| now.saturating_duration_since(b.updated) < SEEN_TTL);
+        self.pruned = now;
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::network::payload::Payload;
+use crate::network::Key;
+
+// lower goes first. within a priority, transfers take turns a frame at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Control,
+    Bulk,
+    // only sent when nothing else is waiting
+    Background,
+}
+
+impl Priority {
+    pub fn of(payload: &Payload) -> Priority {
+        match payload {
+            Payload::File(_, _) | Payload::Push(_, _) | Payload::StreamData(_, _, _) => {
+                Priority::Bulk
+            }
+            _ => Priority::Control,
+        }
+    }
+
+    pub fn of_opcode(opcode: u8) -> Priority {
+        match opcode {
+            Payload::FILE_OPCODE | Payload::PUSH_OPCODE | Payload::STREAM_DATA_OPCODE => {
+                Priority::Bulk
+            }
+            _ => Priority::Control,
+        }
+    }
+}
+
+// a frame taken off the queue, with enough about its transfer to put it back
+pub struct Outgoing {
+    pub key: Key,
+    pub priority: Priority,
+    pub offset: u16,
+    pub retries: u32,
+    pub errors: u32,
+    pub frame: Vec<u8>,
+}
+
+struct Transfer {
+    key: Key,
+    frames: VecDeque<Vec<u8>>,
+    // offset of the frame at the front, its retries and the transfer's errors
+    next: u16,
+    retries: u32,
+    errors: u32,
+}
+
+// encoded frames waiting to go out
+#[derive(Default)]
+pub struct Scheduler {
+    queues: BTreeMap<Priority, VecDeque<Transfer>>,
+    // transfers stopped by a send error, held from the frame that failed
+    interrupted: Vec<(Priority, Transfer)>,
+}
+
+impl Scheduler {
+    pub fn push(&mut self, key: Key, priority: Priority, frames: Vec<Vec<u8>>) {
+        let queue = self.queues.entry(priority).or_default();
+
+        // more frames for a transfer already queued (e.g. relayed chunks) join it
+        match queue.iter_mut().find(|t| t.key == key) {
+            Some(transfer) => transfer.frames.extend(frames),
+            None => queue.push_back(Transfer {
+                key,
+                frames: frames.into(),
+                next: 0,
+                retries: 0,
+                errors: 0,
+            }),
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Outgoing> {
+        let (priority, queue) = self.queues.iter_mut().find(|(_, q)| !q.is_empty())?;
+        let mut transfer = queue.pop_front()?;
+        let outgoing = Outgoing {
+            key: transfer.key,
+            priority: *priority,
+            offset: transfer.next,
+            retries: transfer.retries,
+            errors: transfer.errors,
+            frame: transfer.frames.pop_front()?,
+        };
+
+        // back of the line until it's done
+        transfer.next = transfer.next.wrapping_add(1);
+        transfer.retries = 0;
+        if !transfer.frames.is_empty() {
+            queue.push_back(transfer);
+        }
+
+        Some(outgoing)
+    }
+
+    // puts a frame that couldn't be sent back at the front of its transfer
+    pub fn retry(&mut self, outgoing: Outgoing) {
+        let queue = self.queues.entry(outgoing.priority).or_default();
+        let idx = match queue.iter().position(|t| t.key == outgoing.key) {
+            Some(idx) => idx,
+            None => {
+                queue.push_front(Transfer {
+                    key: outgoing.key,
+                    frames: VecDeque::new(),
+                    next: 0,
+                    retries: 0,
+                    errors: 0,
+                });
+                0
+            }
+        };
+
+        let transfer = &mut queue[idx];
+        transfer.frames.push_front(outgoing.frame);
+        transfer.next = outgoing.offset;
+        transfer.retries = outgoing.retries + 1;
+        transfer.errors = outgoing.errors + 1;
+    }
+
+    // sets the rest of a transfer aside after one of its frames failed for good
+    pub fn interrupt(&mut self, outgoing: Outgoing) {
+        let (key, priority) = (outgoing.key, outgoing.priority);
+        self.retry(outgoing);
+
+        let queue = self.queues.entry(priority).or_default();
+        if let Some(idx) = queue.iter().position(|t| t.key == key) {
+            if let Some(transfer) = queue.remove(idx) {
+                self.interrupted.push((priority, transfer));
+            }
+        }
+    }
+
+    // puts an interrupted transfer back in line, starting with the frame that failed
+    pub fn requeue(&mut self, key: &Key) -> bool {
+        let idx = match self.interrupted.iter().position(|(_, t)| t.key == *key) {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        let (priority, mut transfer) = self.interrupted.remove(idx);
+        transfer.retries = 0;
+        self.queues.entry(priority).or_default().push_back(transfer);
+        true
+    }
+
+    pub fn interrupted(&self) -> Vec<Key> {
+        self.interrupted.iter().map(|(_, t)| t.key).collect()
+    }
+
+    pub fn cancel(&mut self, key: &Key) {
+        for queue in self.queues.values_mut() {
+            queue.retain(|t| t.key != *key);
+        }
+        self.interrupted.retain(|(_, t)| t.key != *key);
+    }
+
+    // drops every transfer of a lower priority, interrupted ones included
+    pub fn discard_below(&mut self, priority: Priority) {
+        self.queues.retain(|p, _| *p <= priority);
+        self.interrupted.retain(|(p, _)| *p <= priority);
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.queues.values().flatten().any(|t| t.key == *key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.values().all(|q| q.is_empty())
+    }
+}
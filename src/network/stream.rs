@@ -0,0 +1,294 @@
+use crossbeam::channel::{bounded, never, Receiver, RecvTimeoutError, Select};
+use pnet::util::MacAddr;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::FloodFileError;
+use crate::network::payload::Payload;
+use crate::network::{Channel, Key, StreamId};
+use crate::protocol::Event;
+
+// input is read and sent this much at a time, about 150 frames once compressed
+pub const SEGMENT_SIZE: usize = 32 * 1024;
+
+// segments read ahead of the slowest receiver, they're kept around for resends
+const WINDOW: u32 = 256;
+
+const SEND_BATCH: usize = 16;
+const OFFER_INTERVAL: Duration = Duration::from_secs(1);
+
+// receivers report their position this often, which doubles as a resend request
+const ACK_INTERVAL: Duration = Duration::from_millis(250);
+
+// a segment asked for again this soon after queueing is most likely still in flight
+const RESEND_HOLDOFF: Duration = Duration::from_millis(250);
+
+// receivers gone quiet this long stop holding the window back
+const PEER_TIMEOUT: Duration = Duration::from_secs(10);
+
+// once everything is read, how long to keep answering receivers that went quiet
+const LINGER: Duration = Duration::from_secs(5);
+
+struct Segment {
+    seq: u32,
+    packet: Payload,
+    key: Key,
+    queued: Instant,
+}
+
+// sends input as stream id, segment by segment as it is read, to whoever asks
+// for it. returns the number of segments once every receiver has them all, and
+// fails if one of them went away before the end.
+pub fn send<R: Read + Send + 'static>(
+    channel: &mut Channel,
+    input: R,
+    id: StreamId,
+    wait: Duration,
+) -> Result<u32, FloodFileError> {
+    let frames = channel.frames();
+    let segments = read_segments(input);
+    let idle = never();
+
+    // the end marker goes in the window too, as segment `total`
+    let mut window: VecDeque<Segment> = VecDeque::new();
+    let mut next = 0;
+    let mut total = None;
+
+    // where each receiver is at, and when we last heard from it. reached keeps
+    // every receiver so one that went quiet still counts at the end.
+    let mut peers: HashMap<MacAddr, (u32, Instant)> = HashMap::new();
+    let mut reached: HashMap<MacAddr, u32> = HashMap::new();
+    let mut heard = Instant::now();
+    let mut offered: Option<Instant> = None;
+
+    loop {
+        let now = Instant::now();
+        peers.retain(|_, (_, seen)| now - *seen < PEER_TIMEOUT);
+
+        match total {
+            Some(total) if !reached.is_empty() && reached.values().all(|at| *at >= total) => {
+                return Ok(total)
+            }
+            Some(_) if now - heard >= LINGER => return Err(FloodFileError::StreamIncomplete),
+            _ if now - heard >= wait => return Err(FloodFileError::StreamTimedOut),
+            _ => (),
+        }
+
+        if peers.is_empty() && offered.is_none_or(|at| now - at >= OFFER_INTERVAL) {
+            channel.queue(Payload::StreamOffer(id))?;
+            offered = Some(now);
+        }
+
+        let base = peers.values().map(|(at, _)| *at).min().unwrap_or(0);
+        while window.front().is_some_and(|s| s.seq < base) {
+            window.pop_front();
+        }
+
+        channel.pump(SEND_BATCH)?;
+
+        // nobody to pace against before the first request, and no point reading
+        // faster than we can send
+        let reading = total.is_none()
+            && !peers.is_empty()
+            && !channel.pending()
+            && next < base.saturating_add(WINDOW);
+
+        let mut select = Select::new();
+        let on_frame = select.recv(&frames);
+        let on_segment = select.recv(if reading { &segments } else { &idle });
+
+        let tick = now + ACK_INTERVAL;
+        let deadline = channel.next_send().map_or(tick, |at| at.min(tick));
+        let operation = match select.select_deadline(deadline) {
+            Ok(operation) => operation,
+            Err(_) => continue,
+        };
+
+        match operation.index() {
+            i if i == on_frame => {
+                let data = operation
+                    .recv(&frames)
+                    .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+
+                // other traffic and stray chunks aren't our concern
+                let (source, packet) = match channel.process(&data) {
+                    Ok(Some(Event::Received(source, packet))) => (source, packet),
+                    _ => continue,
+                };
+
+                match packet {
+                    Payload::StreamRequest(stream, seq) if stream == id => {
+                        // segments before the window are gone, a late joiner
+                        // can't have them and mustn't hold the others back
+                        let kept = window.front().map_or(next, |s| s.seq);
+                        if seq < kept && !peers.contains_key(&source) {
+                            channel.queue(Payload::StreamRefused(id, source))?;
+                            continue;
+                        }
+
+                        heard = Instant::now();
+                        peers.insert(source, (seq, heard));
+                        reached.insert(source, seq);
+
+                        // the receiver is missing seq, unless it's still on its way
+                        if let Some(segment) = window.iter_mut().find(|s| s.seq == seq) {
+                            if !channel.queued(&segment.key)
+                                && heard - segment.queued >= RESEND_HOLDOFF
+                            {
+                                channel.queue_with_key(segment.packet.clone(), segment.key)?;
+                                segment.queued = heard;
+                            }
+                        }
+                    }
+                    Payload::LossReport(key, received, expected) => {
+                        channel.report_loss(key, received, expected)
+                    }
+                    _ => (),
+                }
+            }
+            i if i == on_segment => {
+                let packet = match operation.recv(&segments) {
+                    Ok(Ok(data)) => Payload::StreamData(id, next, data),
+                    Ok(Err(_)) => return Err(FloodFileError::FailedToReadFile),
+
+                    // the reader hung up at the end of the input
+                    Err(_) => {
+                        total = Some(next);
+                        Payload::StreamEnd(id, next)
+                    }
+                };
+
+                let key: Key = rand::random();
+                channel.queue_with_key(packet.clone(), key)?;
+                window.push_back(Segment {
+                    seq: next,
+                    packet,
+                    key,
+                    queued: Instant::now(),
+                });
+                next += 1;
+            }
+            _ => (),
+        }
+    }
+}
+
+// writes stream id to output in order as its segments arrive, returning the
+// number of bytes written
+pub fn receive(
+    channel: &mut Channel,
+    mut output: impl Write,
+    id: StreamId,
+    timeout: Duration,
+) -> Result<u64, FloodFileError> {
+    let frames = channel.frames();
+
+    // segments that overtook a missing one wait here
+    let mut early: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+    let mut next = 0;
+    let mut total = None;
+    let mut written = 0;
+    let mut progress = Instant::now();
+    let mut acked: Option<Instant> = None;
+
+    loop {
+        let now = Instant::now();
+        if total.is_some_and(|total| next >= total) {
+            output
+                .flush()
+                .map_err(|_| FloodFileError::FailedToWriteFile)?;
+
+            // lets the sender finish without waiting on us
+            channel.send(Payload::StreamRequest(id, next))?;
+            return Ok(written);
+        }
+
+        if now - progress >= timeout {
+            return Err(FloodFileError::StreamTimedOut);
+        }
+
+        if acked.is_none_or(|at| now - at >= ACK_INTERVAL) {
+            channel.prune(now);
+            channel.queue(Payload::StreamRequest(id, next))?;
+            for report in channel.loss_reports() {
+                channel.queue(Payload::LossReport(
+                    report.key,
+                    report.received,
+                    report.expected,
+                ))?;
+            }
+            acked = Some(now);
+        }
+
+        channel.pump(SEND_BATCH)?;
+
+        let tick = acked.map_or(now, |at| at + ACK_INTERVAL);
+        let deadline = channel.next_send().map_or(tick, |at| at.min(tick));
+        let data = match frames.recv_deadline(deadline) {
+            Ok(data) => data,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe).into())
+            }
+        };
+
+        let packet = match channel.process(&data) {
+            Ok(Some(Event::Received(_, packet))) => packet,
+            _ => continue,
+        };
+
+        match packet {
+            // the sender just showed up, don't leave it waiting for the next tick
+            Payload::StreamOffer(stream) if stream == id && next == 0 => acked = None,
+            Payload::StreamData(stream, seq, data) if stream == id && seq >= next => {
+                early.insert(seq, data);
+                while let Some(data) = early.remove(&next) {
+                    output
+                        .write_all(&data)
+                        .map_err(|_| FloodFileError::FailedToWriteFile)?;
+                    written += data.len() as u64;
+                    next += 1;
+                    progress = Instant::now();
+                }
+            }
+            Payload::StreamEnd(stream, segments) if stream == id => {
+                total = Some(segments);
+                progress = Instant::now();
+            }
+            Payload::StreamRefused(stream, receiver)
+                if stream == id && receiver == channel.mac_addr() =>
+            {
+                return Err(FloodFileError::StreamRefused)
+            }
+            _ => (),
+        }
+    }
+}
+
+fn read_segments<R: Read + Send + 'static>(mut input: R) -> Receiver<io::Result<Vec<u8>>> {
+    // one segment read ahead, the rest waits in the pipe
+    let (tx, rx) = bounded(1);
+    thread::spawn(move || loop {
+        let mut segment = Vec::with_capacity(SEGMENT_SIZE);
+        match input
+            .by_ref()
+            .take(SEGMENT_SIZE as u64)
+            .read_to_end(&mut segment)
+        {
+            Ok(0) => break,
+            Ok(_) => {
+                if tx.send(Ok(segment)).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                tx.send(Err(e)).ok();
+                break;
+            }
+        }
+    });
+
+    rx
+}
@@ -1,14 +1,31 @@
 use pnet::datalink::NetworkInterface;
+use std::io;
 
 use crate::errors::FloodFileError;
 use crate::network::FileHash;
 
-pub fn compute_filehash(name: &String) -> Result<FileHash, FloodFileError> {
-    let digest = md5::compute(name.clone().into_bytes());
-    match digest.try_into() {
-        Ok(hash) => Ok(hash),
-        _ => Err(FloodFileError::UnableToGenerateHash),
+pub fn compute_filehash(name: &str) -> Result<FileHash, FloodFileError> {
+    let digest = md5::compute(name.as_bytes());
+    Ok(digest.into())
+}
+
+// administratively up, and on unix also with a carrier
+pub fn link_up(interface: &NetworkInterface) -> bool {
+    #[cfg(unix)]
+    return interface.is_up() && interface.is_running();
+
+    #[cfg(not(unix))]
+    return interface.is_up();
+}
+
+// the kernel ran out of room for outgoing frames, worth trying again shortly
+pub fn buffer_full(e: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    if e.raw_os_error() == Some(libc::ENOBUFS) {
+        return true;
     }
+
+    e.kind() == io::ErrorKind::WouldBlock
 }
 
 pub fn usable_interfaces() -> Vec<NetworkInterface> {
@@ -0,0 +1,174 @@
+use pnet::util::MacAddr;
+use rand::prelude::*;
+use std::time::Instant;
+
+use crate::errors::FloodFileError;
+use crate::network::frame::{Frame, CHUNK_SIZE};
+use crate::network::payload::Payload;
+use crate::network::reassembly::{LossReport, Progress, Reassembler};
+use crate::network::{FileHash, Key, Nonce, Vlan};
+
+// the floodfile protocol without any i/o: payloads go in and ethernet frames come
+// out, received frames go in and completed payloads come out. callers own the
+// socket (or capture, or test harness) and decide when to feed it.
+pub struct Protocol {
+    mac: MacAddr,
+    nonce: Nonce,
+    vlan: Option<Vlan>,
+    reassembler: Reassembler,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Received(MacAddr, Payload),
+    FileProgress {
+        source: MacAddr,
+        hash: FileHash,
+        received: usize,
+        total: usize,
+    },
+}
+
+impl Protocol {
+    pub fn new(mac: MacAddr) -> Self {
+        // distinguishes this instance from others sharing the same mac
+        Self::with_nonce(mac, rand::thread_rng().gen())
+    }
+
+    pub fn with_nonce(mac: MacAddr, nonce: Nonce) -> Self {
+        Self {
+            mac,
+            nonce,
+            vlan: None,
+            reassembler: Reassembler::default(),
+        }
+    }
+
+    pub fn encode(&self, payload: &Payload) -> Result<Vec<Vec<u8>>, FloodFileError> {
+        self.encode_with_key(payload, rand::thread_rng().gen())
+    }
+
+    pub fn encode_with_key(
+        &self,
+        payload: &Payload,
+        key: Key,
+    ) -> Result<Vec<Vec<u8>>, FloodFileError> {
+        let data = payload.serialize();
+
+        // chunk packet into maximum possible size
+        let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+        let total = chunks.len();
+
+        if total >= u16::MAX as usize {
+            return Err(FloodFileError::FileTooLarge);
+        }
+
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(offset, chunk)| {
+                self.frame(payload.opcode(), offset as u16, total as u16, key, chunk)
+            })
+            .collect()
+    }
+
+    pub fn frame(
+        &self,
+        opcode: u8,
+        offset: u16,
+        total: u16,
+        key: Key,
+        chunk: &[u8],
+    ) -> Result<Vec<u8>, FloodFileError> {
+        Frame {
+            source: self.mac,
+            relayed_by: None,
+            vlan: self.vlan,
+            opcode,
+            hops: 0,
+            offset,
+            total,
+            key,
+            nonce: self.nonce,
+            chunk: chunk.to_vec(),
+        }
+        .encode()
+    }
+
+    pub fn decode(&mut self, buffer: &[u8]) -> Result<Option<Event>, FloodFileError> {
+        let (frame, _) = match Frame::decode(buffer) {
+            Some(frame) => frame,
+            _ => return Ok(None),
+        };
+
+        if !self.accepts(&frame) {
+            return Ok(None);
+        }
+
+        let (source, key) = (frame.source, frame.key);
+        let (opcode, data) = match self.reassembler.push(frame)? {
+            Progress::Complete { opcode, data } => (opcode, data),
+            Progress::Partial { received, total } => {
+                return Ok(self.progress(source, key, received, total))
+            }
+        };
+
+        let payload =
+            Payload::deserialize(opcode, &data).ok_or(FloodFileError::FailedToDeserializeArp)?;
+
+        Ok(Some(Event::Received(source, payload)))
+    }
+
+    fn progress(&self, source: MacAddr, key: Key, received: usize, total: usize) -> Option<Event> {
+        // file payloads lead with their hash, so progress can be tied to a download
+        // as soon as the first chunk has arrived
+        let head = self.reassembler.first_chunk(source, key)?;
+        if self.reassembler.opcode(source, key)? != Payload::FILE_OPCODE {
+            return None;
+        }
+
+        let hash: FileHash = head.get(0..16)?.try_into().ok()?;
+        Some(Event::FileProgress {
+            source,
+            hash,
+            received,
+            total,
+        })
+    }
+
+    pub fn loss_reports(&mut self) -> Vec<LossReport> {
+        self.reassembler.loss_reports()
+    }
+
+    pub fn prune(&mut self, now: Instant) -> usize {
+        self.reassembler.prune(now)
+    }
+
+    pub fn receiving(&self) -> bool {
+        self.reassembler.pending() > 0
+    }
+
+    pub fn accepts(&self, frame: &Frame) -> bool {
+        // accept untagged frames, or tagged frames on our vlan (any vlan if unset)
+        if let (Some(ours), Some(theirs)) = (self.vlan, frame.vlan) {
+            if ours.id != theirs.id {
+                return false;
+            }
+        }
+
+        // drop frames we sent that were looped back by the driver
+        !(frame.source == self.mac && frame.nonce == self.nonce)
+    }
+
+    pub fn mac_addr(&self) -> MacAddr {
+        self.mac
+    }
+
+    pub fn set_vlan(&mut self, vlan: Option<Vlan>) {
+        self.vlan = vlan;
+    }
+
+    pub fn vlan(&self) -> Option<Vlan> {
+        self.vlan
+    }
+}
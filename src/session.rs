@@ -0,0 +1,459 @@
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::errors::FloodFileError;
+use crate::network::filter::FilterRule;
+use crate::network::payload::{CatalogEntry, Payload};
+use crate::network::relay::RelayConfig;
+use crate::network::utils::usable_interfaces;
+use crate::network::{Channel, FileHash, Vlan};
+use config::{Config, MAX_ADVERTISE_INTERVAL};
+use push::{PushId, PushPolicy};
+use worker::worker;
+
+pub mod config;
+pub mod push;
+pub mod timers;
+pub mod worker;
+
+// how long a query waits on the worker before giving up
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// events nobody reads are dropped past this many, rather than piling up
+const EVENT_BACKLOG: usize = 1024;
+
+pub type ShareId = FileHash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DownloadId(pub u64);
+
+#[derive(Debug, Clone)]
+pub struct RemoteShare {
+    pub id: ShareId,
+    pub name: String,
+    pub peer: MacAddr,
+    pub interface: String,
+    pub carousel: bool,
+}
+
+// when, and for how long, one of our files is offered
+#[derive(Debug, Clone, Default)]
+pub struct ShareOptions {
+    pub start_at: Option<SystemTime>,
+    pub stop_after: Option<Duration>,
+    pub max_downloads: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalShare {
+    pub path: String,
+    pub starts: Option<Instant>,
+    pub expires: Option<Instant>,
+    pub max_downloads: Option<u32>,
+    pub downloads: u32,
+}
+
+impl LocalShare {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            starts: None,
+            expires: None,
+            max_downloads: None,
+            downloads: 0,
+        }
+    }
+
+    // none if the start or stop is too far out to keep track of
+    pub fn scheduled(path: String, options: &ShareOptions, now: Instant) -> Option<Self> {
+        // a start time already passed means right away, the clock is only read once
+        let starts = match options
+            .start_at
+            .and_then(|at| at.duration_since(SystemTime::now()).ok())
+        {
+            Some(wait) => Some(now.checked_add(wait)?),
+            None => None,
+        };
+        let expires = match options.stop_after {
+            Some(after) => Some(starts.unwrap_or(now).checked_add(after)?),
+            None => None,
+        };
+
+        Some(Self {
+            path,
+            starts,
+            expires,
+            max_downloads: options.max_downloads,
+            downloads: 0,
+        })
+    }
+
+    pub fn live(&self, now: Instant) -> bool {
+        self.starts.is_none_or(|at| now >= at)
+    }
+
+    pub fn exhausted(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|at| now >= at)
+            || self.max_downloads.is_some_and(|max| self.downloads >= max)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub peer: MacAddr,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Sharing(String),
+    ShareStopped(String),
+    PushOffered {
+        id: PushId,
+        peer: MacAddr,
+        name: String,
+        size: usize,
+    },
+    PushSaved(PathBuf),
+    SnippetReceived(Snippet),
+    CarouselEnded(String),
+    ShareDiscovered(RemoteShare),
+    ShareWithdrawn(ShareId),
+    DownloadProgress {
+        id: DownloadId,
+        received: usize,
+        total: usize,
+    },
+    DownloadComplete {
+        id: DownloadId,
+        path: PathBuf,
+    },
+    DownloadFailed {
+        id: DownloadId,
+        error: String,
+    },
+    Catalog {
+        peer: MacAddr,
+        entries: Vec<CatalogEntry>,
+    },
+    Interfaces(Vec<NetworkInterface>),
+    Paused(String),
+    Resumed(String),
+    FailedOver(String),
+    Notice(String),
+    Error(String),
+}
+
+pub enum Command {
+    Share(String, ShareOptions),
+    Unshare(String),
+    Carousel(String, Duration),
+    Push(String),
+    SendSnippet(String),
+    Snippets(Sender<Vec<Snippet>>),
+    AcceptPush(PushId),
+    RejectPush(PushId),
+    SetPushPolicy(PushPolicy),
+    Trust(MacAddr),
+    Untrust(MacAddr),
+    PushSettings(Sender<(PushPolicy, Vec<MacAddr>)>),
+    Download(ShareId, DownloadId),
+    Cancel(DownloadId),
+    ChangeInterface(String),
+    AddInterface(String),
+    RemoveInterface(String),
+    SetDownloadDir(PathBuf),
+    SetAdvertiseInterval(Duration),
+    RequestCatalog(MacAddr),
+    AddFilter(FilterRule),
+    RemoveFilter(FilterRule),
+    SetVlan(Option<Vlan>),
+    SetRelay(Option<RelayConfig>),
+    Record(Option<PathBuf>),
+    RemoteShares(Sender<Vec<RemoteShare>>),
+    LocalShares(Sender<Vec<LocalShare>>),
+    Peers(Sender<Vec<MacAddr>>),
+    Filters(Sender<Vec<FilterRule>>),
+    ActiveInterfaces(Sender<Vec<String>>),
+    Shutdown(Sender<()>),
+}
+
+// a running floodfile node. clones share the same worker and event stream, so
+// each event is delivered to exactly one of them.
+#[derive(Clone)]
+pub struct Session {
+    commands: Sender<Command>,
+    events: Receiver<Event>,
+    next_download: Arc<AtomicU64>,
+}
+
+pub struct DownloadHandle {
+    id: DownloadId,
+    share: ShareId,
+    commands: Sender<Command>,
+}
+
+impl Session {
+    pub fn open(interface: &str) -> Result<Session, FloodFileError> {
+        let interface = usable_interfaces()
+            .into_iter()
+            .find(|i| i.name == interface)
+            .ok_or(FloodFileError::InterfaceNotFound)?;
+
+        Self::start(Config::from(interface)?)
+    }
+
+    pub fn open_default() -> Result<Session, FloodFileError> {
+        Self::start(Config::new()?)
+    }
+
+    // runs on a channel opened elsewhere, such as one on a Loopback bus
+    pub fn with_channel(channel: Channel) -> Result<Session, FloodFileError> {
+        Self::with_channels(vec![channel])
+    }
+
+    // one link per channel, the first is the primary
+    pub fn with_channels(channels: Vec<Channel>) -> Result<Session, FloodFileError> {
+        if channels.is_empty() {
+            return Err(FloodFileError::InterfaceNotFound);
+        }
+
+        Self::start(Config::with_channels(channels))
+    }
+
+    fn start(cfg: Config) -> Result<Session, FloodFileError> {
+        let (commands, commands_rx) = unbounded::<Command>();
+        let (events_tx, events) = bounded::<Event>(EVENT_BACKLOG);
+
+        thread::spawn(move || worker(cfg, commands_rx, events_tx));
+
+        Ok(Session {
+            commands,
+            events,
+            next_download: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub fn events(&self) -> &Receiver<Event> {
+        &self.events
+    }
+
+    pub fn share(&self, path: impl AsRef<Path>) -> Result<(), FloodFileError> {
+        self.share_with(path, ShareOptions::default())
+    }
+
+    pub fn share_with(
+        &self,
+        path: impl AsRef<Path>,
+        options: ShareOptions,
+    ) -> Result<(), FloodFileError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(FloodFileError::FailedToReadFile);
+        }
+
+        let path = path.to_str().ok_or(FloodFileError::FailedToReadFile)?;
+        self.send(Command::Share(path.to_string(), options))
+    }
+
+    // loops the file's chunks for a while, then leaves it shared as usual
+    pub fn carousel(
+        &self,
+        path: impl AsRef<Path>,
+        duration: Duration,
+    ) -> Result<(), FloodFileError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(FloodFileError::FailedToReadFile);
+        }
+
+        let path = path.to_str().ok_or(FloodFileError::FailedToReadFile)?;
+        self.send(Command::Carousel(path.to_string(), duration))
+    }
+
+    // hands the file to everyone listening right away, without advertising it
+    pub fn push(&self, path: impl AsRef<Path>) -> Result<(), FloodFileError> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(FloodFileError::FailedToReadFile);
+        }
+
+        let path = path.to_str().ok_or(FloodFileError::FailedToReadFile)?;
+        self.send(Command::Push(path.to_string()))
+    }
+
+    pub fn accept_push(&self, id: PushId) -> Result<(), FloodFileError> {
+        self.send(Command::AcceptPush(id))
+    }
+
+    pub fn reject_push(&self, id: PushId) -> Result<(), FloodFileError> {
+        self.send(Command::RejectPush(id))
+    }
+
+    pub fn set_push_policy(&self, policy: PushPolicy) -> Result<(), FloodFileError> {
+        self.send(Command::SetPushPolicy(policy))
+    }
+
+    pub fn trust(&self, peer: MacAddr) -> Result<(), FloodFileError> {
+        self.send(Command::Trust(peer))
+    }
+
+    pub fn untrust(&self, peer: MacAddr) -> Result<(), FloodFileError> {
+        self.send(Command::Untrust(peer))
+    }
+
+    pub fn push_settings(&self) -> Result<(PushPolicy, Vec<MacAddr>), FloodFileError> {
+        self.query(Command::PushSettings)
+    }
+
+    pub fn send_snippet(&self, text: &str) -> Result<(), FloodFileError> {
+        // an empty payload has no chunks, so it would never go out
+        if text.is_empty() {
+            return Err(FloodFileError::EmptySnippet);
+        }
+        if text.len() > Payload::MAX_SNIPPET_LEN {
+            return Err(FloodFileError::SnippetTooLarge);
+        }
+
+        self.send(Command::SendSnippet(text.to_string()))
+    }
+
+    // received snippets, oldest first
+    pub fn snippets(&self) -> Result<Vec<Snippet>, FloodFileError> {
+        self.query(Command::Snippets)
+    }
+
+    pub fn unshare(&self, path: impl AsRef<Path>) -> Result<(), FloodFileError> {
+        let path = path
+            .as_ref()
+            .to_str()
+            .ok_or(FloodFileError::FailedToReadFile)?;
+        self.send(Command::Unshare(path.to_string()))
+    }
+
+    pub fn download(&self, share: ShareId) -> Result<DownloadHandle, FloodFileError> {
+        let id = DownloadId(self.next_download.fetch_add(1, Ordering::Relaxed));
+        self.send(Command::Download(share, id))?;
+
+        Ok(DownloadHandle {
+            id,
+            share,
+            commands: self.commands.clone(),
+        })
+    }
+
+    pub fn cancel(&self, download: &DownloadHandle) -> Result<(), FloodFileError> {
+        download.cancel()
+    }
+
+    pub fn remote_shares(&self) -> Result<Vec<RemoteShare>, FloodFileError> {
+        self.query(Command::RemoteShares)
+    }
+
+    pub fn local_shares(&self) -> Result<Vec<LocalShare>, FloodFileError> {
+        self.query(Command::LocalShares)
+    }
+
+    pub fn peers(&self) -> Result<Vec<MacAddr>, FloodFileError> {
+        self.query(Command::Peers)
+    }
+
+    pub fn request_catalog(&self, peer: MacAddr) -> Result<(), FloodFileError> {
+        self.send(Command::RequestCatalog(peer))
+    }
+
+    pub fn change_interface(&self, interface: &str) -> Result<(), FloodFileError> {
+        self.send(Command::ChangeInterface(interface.to_string()))
+    }
+
+    // share on another interface alongside the current ones
+    pub fn add_interface(&self, interface: &str) -> Result<(), FloodFileError> {
+        self.send(Command::AddInterface(interface.to_string()))
+    }
+
+    pub fn remove_interface(&self, interface: &str) -> Result<(), FloodFileError> {
+        self.send(Command::RemoveInterface(interface.to_string()))
+    }
+
+    pub fn active_interfaces(&self) -> Result<Vec<String>, FloodFileError> {
+        self.query(Command::ActiveInterfaces)
+    }
+
+    pub fn set_download_dir(&self, path: impl AsRef<Path>) -> Result<(), FloodFileError> {
+        self.send(Command::SetDownloadDir(path.as_ref().to_path_buf()))
+    }
+
+    // at most a day, the timer can't be set arbitrarily far out
+    pub fn set_advertise_interval(&self, interval: Duration) -> Result<(), FloodFileError> {
+        let interval = interval.min(MAX_ADVERTISE_INTERVAL);
+        self.send(Command::SetAdvertiseInterval(interval))
+    }
+
+    pub fn filters(&self) -> Result<Vec<FilterRule>, FloodFileError> {
+        self.query(Command::Filters)
+    }
+
+    pub fn add_filter(&self, rule: FilterRule) -> Result<(), FloodFileError> {
+        self.send(Command::AddFilter(rule))
+    }
+
+    pub fn remove_filter(&self, rule: FilterRule) -> Result<(), FloodFileError> {
+        self.send(Command::RemoveFilter(rule))
+    }
+
+    pub fn set_vlan(&self, vlan: Option<Vlan>) -> Result<(), FloodFileError> {
+        self.send(Command::SetVlan(vlan))
+    }
+
+    // forward traffic between every interface we're on, None stops relaying
+    pub fn set_relay(&self, relay: Option<RelayConfig>) -> Result<(), FloodFileError> {
+        self.send(Command::SetRelay(relay))
+    }
+
+    pub fn record(&self, path: Option<PathBuf>) -> Result<(), FloodFileError> {
+        self.send(Command::Record(path))
+    }
+
+    // withdraws every share from the network, finishes what's already been
+    // received and closes the interface. the other clones stop working too.
+    pub fn shutdown(&self) -> Result<(), FloodFileError> {
+        self.query(Command::Shutdown)
+    }
+
+    fn send(&self, command: Command) -> Result<(), FloodFileError> {
+        self.commands
+            .send(command)
+            .map_err(|_| FloodFileError::SessionClosed)
+    }
+
+    fn query<T>(&self, command: fn(Sender<T>) -> Command) -> Result<T, FloodFileError> {
+        let (reply_tx, reply_rx) = bounded::<T>(1);
+        self.send(command(reply_tx))?;
+
+        reply_rx
+            .recv_timeout(QUERY_TIMEOUT)
+            .map_err(|_| FloodFileError::SessionClosed)
+    }
+}
+
+impl DownloadHandle {
+    pub fn id(&self) -> DownloadId {
+        self.id
+    }
+
+    pub fn share(&self) -> ShareId {
+        self.share
+    }
+
+    pub fn cancel(&self) -> Result<(), FloodFileError> {
+        self.commands
+            .send(Command::Cancel(self.id))
+            .map_err(|_| FloodFileError::SessionClosed)
+    }
+}
@@ -0,0 +1,306 @@
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::errors::FloodFileError;
+use crate::network::filter::Filters;
+use crate::network::frame::CHUNK_SIZE;
+use crate::network::payload::{CatalogEntry, Payload};
+use crate::network::relay::Relay;
+use crate::network::utils::usable_interfaces;
+use crate::network::{Channel, FileHash, Key, Vlan};
+use crate::session::push::Pushes;
+use crate::session::timers::{Timer, Timers};
+use crate::session::{DownloadId, LocalShare, RemoteShare, Snippet};
+
+// older snippets are forgotten past this many
+pub const MAX_SNIPPETS: usize = 50;
+
+const DEFAULT_ADVERTISE_INTERVAL: Duration = Duration::from_secs(30);
+pub const MAX_ADVERTISE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const LOSS_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(5);
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+const DISCOVER_REPLY_WINDOW: Duration = Duration::from_secs(2);
+const CAROUSEL_TICK: Duration = Duration::from_millis(100);
+
+// an interface we're sharing on
+pub struct Link {
+    pub channel: Channel,
+    pub paused: bool,
+}
+
+// a shared file looped a few chunks at a time, so peers tuning in late can
+// collect every chunk without asking for it
+pub struct Carousel {
+    pub key: Key,
+    pub data: Vec<u8>,
+    pub next: usize,
+    pub until: Instant,
+}
+
+impl Carousel {
+    pub fn total(&self) -> usize {
+        self.data.len().div_ceil(CHUNK_SIZE)
+    }
+}
+
+pub struct Config {
+    pub links: Vec<Link>,
+    pub shared: HashMap<FileHash, RemoteShare>,
+    pub sharing: HashMap<FileHash, LocalShare>,
+    pub carousels: HashMap<FileHash, Carousel>,
+    pub requested: HashMap<FileHash, DownloadId>,
+    pub peers: HashMap<MacAddr, String>,
+    pub catalogs: HashMap<MacAddr, Vec<Option<Vec<CatalogEntry>>>>,
+    pub filters: Filters,
+    pub pushes: Pushes,
+    pub snippets: VecDeque<Snippet>,
+    pub download_dir: PathBuf,
+    pub vlan: Option<Vlan>,
+    pub relay: Option<Relay>,
+    pub advertise_interval: Duration,
+    pub timers: Timers,
+}
+
+impl Config {
+    pub fn new() -> Result<Self, FloodFileError> {
+        let interface = usable_interfaces()
+            .into_iter()
+            .next()
+            .ok_or(FloodFileError::InterfaceNotFound)?;
+
+        Config::from(interface)
+    }
+
+    pub fn from(interface: NetworkInterface) -> Result<Self, FloodFileError> {
+        Ok(Config::with_channels(vec![Channel::new(interface)?]))
+    }
+
+    pub fn with_channels(channels: Vec<Channel>) -> Self {
+        let links = channels
+            .into_iter()
+            .map(|channel| Link {
+                channel,
+                paused: false,
+            })
+            .collect();
+
+        let mut cfg = Config {
+            links,
+            shared: HashMap::new(),
+            sharing: HashMap::new(),
+            carousels: HashMap::new(),
+            requested: HashMap::new(),
+            peers: HashMap::new(),
+            catalogs: HashMap::new(),
+            filters: Filters::default(),
+            pushes: Pushes::default(),
+            snippets: VecDeque::new(),
+            download_dir: std::env::temp_dir(),
+            vlan: None,
+            relay: None,
+            advertise_interval: DEFAULT_ADVERTISE_INTERVAL,
+            timers: Timers::default(),
+        };
+
+        cfg.schedule_advertise();
+        cfg
+    }
+
+    fn open(&self, interface: NetworkInterface) -> Result<Link, FloodFileError> {
+        let mut channel = Channel::new(interface)?;
+        channel.set_vlan(self.vlan);
+
+        Ok(Link {
+            channel,
+            paused: false,
+        })
+    }
+
+    pub fn link(&self, name: &str) -> Option<usize> {
+        self.links
+            .iter()
+            .position(|l| l.channel.interface_name() == name)
+    }
+
+    pub fn add_interface(&mut self, interface: NetworkInterface) -> Result<(), FloodFileError> {
+        if self.link(&interface.name).is_some() {
+            return Ok(());
+        }
+
+        let link = self.open(interface)?;
+        self.links.push(link);
+        Ok(())
+    }
+
+    pub fn remove_interface(&mut self, name: &str) {
+        let idx = match self.link(name) {
+            Some(idx) => idx,
+            None => return,
+        };
+
+        // forget whatever was only reachable through it
+        self.links.remove(idx);
+        self.shared.retain(|_, s| s.interface != name);
+        self.peers.retain(|_, interface| interface != name);
+    }
+
+    pub fn change_interface(&mut self, interface: NetworkInterface) -> Result<(), FloodFileError> {
+        // settings survive the switch, everything learnt from the old networks doesn't
+        let link = self.open(interface)?;
+        self.links = vec![link];
+        self.shared.clear();
+        self.sharing.clear();
+        self.carousels.clear();
+        self.requested.clear();
+        self.peers.clear();
+        self.catalogs.clear();
+
+        self.schedule_advertise();
+        Ok(())
+    }
+
+    pub fn reopen(
+        &mut self,
+        idx: usize,
+        interface: NetworkInterface,
+    ) -> Result<(), FloodFileError> {
+        // a fresh socket on the same settings, everything else is kept
+        let mut link = self.open(interface)?;
+        link.channel.take_queue(&mut self.links[idx].channel);
+        self.links[idx] = link;
+        Ok(())
+    }
+
+    pub fn set_vlan(&mut self, vlan: Option<Vlan>) {
+        self.vlan = vlan;
+        for link in self.links.iter_mut() {
+            link.channel.set_vlan(vlan);
+        }
+    }
+
+    pub fn send_on(&mut self, idx: usize, packet: Payload) -> Result<(), FloodFileError> {
+        match self.links.get_mut(idx) {
+            Some(link) if !link.paused => link.channel.queue(packet),
+            _ => Ok(()),
+        }
+    }
+
+    // queued under a key taken from the hash, so cancelling can take it back out
+    pub fn request(&mut self, idx: usize, hash: FileHash) -> Result<(), FloodFileError> {
+        match self.links.get_mut(idx) {
+            Some(link) if !link.paused => link
+                .channel
+                .queue_with_key(Payload::DownloadRequest(hash), request_key(&hash)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn cancel_request(&mut self, hash: &FileHash) {
+        for link in self.links.iter_mut() {
+            link.channel.cancel(&request_key(hash));
+        }
+    }
+
+    pub fn broadcast(&mut self, packet: &Payload) -> Result<(), FloodFileError> {
+        // every live link gets a copy, the first failure is reported
+        let mut result = Ok(());
+        for link in self.links.iter_mut().filter(|l| !l.paused) {
+            if let Err(e) = link.channel.queue(packet.clone()) {
+                result = result.and(Err(e));
+            }
+        }
+
+        result
+    }
+
+    pub fn paused(&self) -> bool {
+        self.links.iter().all(|l| l.paused)
+    }
+
+    // wakes for the next share to start or stop, if any
+    pub fn schedule_shares(&mut self) {
+        let next = self
+            .sharing
+            .values()
+            .flat_map(|s| [s.starts, s.expires])
+            .flatten()
+            .min();
+
+        match next {
+            Some(at) => self.timers.schedule(at, Timer::Shares),
+            None => self.timers.cancel(Timer::Shares),
+        }
+    }
+
+    pub fn schedule_carousel(&mut self) {
+        let at = Instant::now() + CAROUSEL_TICK;
+        self.timers.schedule(at, Timer::Carousel);
+    }
+
+    pub fn schedule_prune(&mut self) {
+        let at = Instant::now() + PRUNE_INTERVAL;
+        self.timers.schedule(at, Timer::Prune);
+    }
+
+    // pushed back by every failure, so a link that keeps failing is tried less
+    pub fn schedule_retry(&mut self) {
+        let at = Instant::now() + RETRY_INTERVAL;
+        self.timers.schedule(at, Timer::Retry);
+    }
+
+    pub fn receiving(&self) -> bool {
+        self.links.iter().any(|l| l.channel.receiving())
+    }
+
+    // loss reports and pruning only have work while a transfer is arriving, so
+    // its first frame arms them and they stop once nothing is left
+    pub fn schedule_transfer_timers(&mut self) {
+        if !self.receiving() {
+            return;
+        }
+
+        if self.timers.deadline(Timer::LossReport).is_none() {
+            self.schedule_loss_report();
+        }
+        if self.timers.deadline(Timer::Prune).is_none() {
+            self.schedule_prune();
+        }
+    }
+
+    pub fn schedule_loss_report(&mut self) {
+        let at = Instant::now() + LOSS_REPORT_INTERVAL;
+        self.timers.schedule(at, Timer::LossReport);
+    }
+
+    pub fn schedule_advertise(&mut self) {
+        // jitter by +/- 25% so peers joining together don't advertise in lockstep
+        let jitter = rand::thread_rng().gen_range(0.75..1.25);
+        let at = Instant::now() + self.advertise_interval.mul_f64(jitter);
+        self.timers.schedule(at, Timer::Advertise);
+    }
+
+    // answers a discover by bringing the next re-advertise forward, to a random
+    // point so everyone hearing the same discover doesn't reply at once
+    pub fn schedule_discover_reply(&mut self) {
+        let jitter = rand::thread_rng().gen_range(0.0..1.0);
+        let at = Instant::now() + DISCOVER_REPLY_WINDOW.mul_f64(jitter);
+        if self
+            .timers
+            .deadline(Timer::Advertise)
+            .is_none_or(|next| at < next)
+        {
+            self.timers.schedule(at, Timer::Advertise);
+        }
+    }
+}
+
+fn request_key(hash: &FileHash) -> Key {
+    let mut key = [0; 8];
+    key.copy_from_slice(&hash[..8]);
+    key
+}
@@ -0,0 +1,90 @@
+use pnet::util::MacAddr;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+// pushes waiting on the user beyond either limit drop the oldest
+const MAX_PENDING_PUSHES: usize = 8;
+const MAX_PENDING_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PushId(pub u64);
+
+// what happens to files peers push at us unasked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PushPolicy {
+    Ignore,
+    #[default]
+    Prompt,
+    // trusted peers' pushes are saved straight away, everyone else's prompt
+    AcceptTrusted,
+}
+
+pub enum Decision {
+    Ignore,
+    Prompt,
+    Accept,
+}
+
+pub struct PendingPush {
+    pub id: PushId,
+    pub peer: MacAddr,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Pushes {
+    pub policy: PushPolicy,
+    pub trusted: HashSet<MacAddr>,
+    pending: VecDeque<PendingPush>,
+    next_id: u64,
+}
+
+impl fmt::Display for PushPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let policy = match self {
+            PushPolicy::Ignore => "ignore",
+            PushPolicy::Prompt => "prompt",
+            PushPolicy::AcceptTrusted => "accept from trusted peers",
+        };
+        write!(f, "{0}", policy)
+    }
+}
+
+impl Pushes {
+    pub fn decide(&self, peer: MacAddr) -> Decision {
+        match self.policy {
+            PushPolicy::Ignore => Decision::Ignore,
+            PushPolicy::AcceptTrusted if self.trusted.contains(&peer) => Decision::Accept,
+            _ => Decision::Prompt,
+        }
+    }
+
+    pub fn offer(&mut self, peer: MacAddr, name: String, data: Vec<u8>) -> PushId {
+        let id = PushId(self.next_id);
+        self.next_id += 1;
+
+        while self.pending.len() >= MAX_PENDING_PUSHES
+            || (!self.pending.is_empty() && self.pending_bytes() + data.len() > MAX_PENDING_BYTES)
+        {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(PendingPush {
+            id,
+            peer,
+            name,
+            data,
+        });
+
+        id
+    }
+
+    pub fn pending_bytes(&self) -> usize {
+        self.pending.iter().map(|p| p.data.len()).sum()
+    }
+
+    pub fn take(&mut self, id: PushId) -> Option<PendingPush> {
+        let idx = self.pending.iter().position(|p| p.id == id)?;
+        self.pending.remove(idx)
+    }
+}
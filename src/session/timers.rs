@@ -0,0 +1,56 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Timer {
+    Advertise,
+    LossReport,
+    Carousel,
+    Shares,
+    Prune,
+    Retry,
+}
+
+// deadlines the worker sleeps until, earliest first
+#[derive(Default)]
+pub struct Timers {
+    queue: BinaryHeap<Reverse<(Instant, Timer)>>,
+}
+
+impl Timers {
+    // replaces any pending deadline for the same timer
+    pub fn schedule(&mut self, at: Instant, timer: Timer) {
+        self.cancel(timer);
+        self.queue.push(Reverse((at, timer)));
+    }
+
+    pub fn cancel(&mut self, timer: Timer) {
+        self.queue.retain(|Reverse((_, t))| *t != timer);
+    }
+
+    pub fn deadline(&self, timer: Timer) -> Option<Instant> {
+        self.queue
+            .iter()
+            .find(|Reverse((_, t))| *t == timer)
+            .map(|Reverse((at, _))| *at)
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.queue.peek().map(|Reverse((at, _))| *at)
+    }
+
+    pub fn expired(&mut self, now: Instant) -> Vec<Timer> {
+        let mut expired = vec![];
+        while let Some(Reverse((at, timer))) = self.queue.peek() {
+            if *at > now {
+                break;
+            }
+
+            expired.push(*timer);
+            self.queue.pop();
+        }
+
+        expired
+    }
+}
@@ -0,0 +1,1145 @@
+use crossbeam::channel::{at, never, Receiver, Select, Sender, TrySendError};
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::FloodFileError;
+use crate::files;
+use crate::network::filter::TrafficKind;
+use crate::network::frame::{Frame, CHUNK_SIZE};
+use crate::network::monitor::{link_changes, InterfaceMonitor, LinkChange};
+use crate::network::payload::{CatalogEntry, Payload};
+use crate::network::relay::Relay;
+use crate::network::scheduler::Priority;
+use crate::network::utils::{compute_filehash, usable_interfaces};
+use crate::network::FileHash;
+use crate::protocol::Event as ProtocolEvent;
+use crate::session::config::{Carousel, Config, MAX_SNIPPETS};
+use crate::session::push::Decision;
+use crate::session::timers::Timer;
+use crate::session::{Command, Event, LocalShare, RemoteShare, ShareOptions, Snippet};
+
+const CATALOG_PAGE_SIZE: usize = 8;
+
+// frames each link sends per pass, small enough to keep commands responsive
+const SEND_BATCH: usize = 16;
+
+// chunks of each carousel queued per tick
+const CAROUSEL_BURST: usize = 8;
+
+pub fn worker(mut cfg: Config, commands: Receiver<Command>, events: Sender<Event>) {
+    let monitor = InterfaceMonitor::start();
+    let links = monitor.changes();
+    discover(&mut cfg, &events);
+
+    loop {
+        pump(&mut cfg, &events, SEND_BATCH);
+        let wake = next_send(&cfg);
+
+        // rebuilt every pass, the links change with the interfaces and the
+        // next deadline with every schedule
+        let frames: Vec<_> = cfg.links.iter().map(|l| l.channel.frames()).collect();
+        let timer = match cfg.timers.next_deadline() {
+            Some(deadline) => at(deadline),
+            None => never(),
+        };
+
+        let mut select = Select::new();
+        let on_command = select.recv(&commands);
+        let on_link = select.recv(&links);
+        let on_timer = select.recv(&timer);
+        let on_frames: Vec<usize> = frames.iter().map(|f| select.recv(f)).collect();
+
+        // with frames still queued only wait until the next can go out
+        let operation = match wake {
+            Some(deadline) => match select.select_deadline(deadline) {
+                Ok(operation) => operation,
+                Err(_) => continue,
+            },
+            None => select.select(),
+        };
+        match operation.index() {
+            i if i == on_command => match operation.recv(&commands) {
+                Ok(Command::Shutdown(reply)) => {
+                    shutdown(&mut cfg, &events);
+
+                    // closes the channels, the listeners are joined before we reply
+                    drop(cfg);
+                    reply.send(()).ok();
+                    return;
+                }
+                Ok(command) => handle_command(&mut cfg, &events, command),
+                Err(_) => return,
+            },
+            i if i == on_link => {
+                operation.recv(&links).ok();
+
+                // changes come in bursts, one look at the interfaces covers them all
+                while links.try_recv().is_ok() {}
+                handle_link_change(&mut cfg, &events);
+            }
+            i if i == on_timer => {
+                operation.recv(&timer).ok();
+                for timer in cfg.timers.expired(Instant::now()) {
+                    handle_timer(&mut cfg, &events, timer);
+                }
+            }
+            i => {
+                let via = on_frames.iter().position(|f| *f == i).unwrap_or_default();
+                if let Ok(data) = operation.recv(&frames[via]) {
+                    handle_frame(&mut cfg, &events, via, &data);
+                    cfg.schedule_transfer_timers();
+                }
+            }
+        }
+    }
+}
+
+fn handle_frame(cfg: &mut Config, events: &Sender<Event>, via: usize, data: &[u8]) {
+    if cfg.relay.is_some() {
+        relay_frame(cfg, events, via, data);
+    }
+
+    match cfg.links[via].channel.process(data) {
+        Ok(Some(ProtocolEvent::Received(source, packet))) => {
+            if !cfg.filters.permits(TrafficKind::of(&packet), source) {
+                return;
+            }
+
+            let interface = cfg.links[via].channel.interface_name();
+            cfg.peers.insert(source, interface);
+            handle_payload(cfg, events, via, source, packet);
+        }
+        Ok(Some(ProtocolEvent::FileProgress {
+            hash,
+            received,
+            total,
+            ..
+        })) => {
+            // one event per percent is plenty, not one per chunk
+            let percent = |received: usize| received * 100 / total.max(1);
+            if received != total && percent(received) == percent(received.saturating_sub(1)) {
+                return;
+            }
+
+            if let Some(id) = cfg.requested.get(&hash) {
+                let id = *id;
+                send_event(
+                    events,
+                    Event::DownloadProgress {
+                        id,
+                        received,
+                        total,
+                    },
+                );
+            }
+        }
+        _ => (),
+    }
+}
+
+fn relay_frame(cfg: &mut Config, events: &Sender<Event>, via: usize, data: &[u8]) {
+    let frame = match Frame::decode(data) {
+        Some((frame, _)) => frame,
+        None => return,
+    };
+
+    // our own frames, and our own forwards echoed back, stay where they are
+    let ours = |mac| cfg.links.iter().any(|l| l.channel.mac_addr() == mac);
+    if !cfg.links[via].channel.accepts(&frame)
+        || ours(frame.source)
+        || frame.relayed_by.is_some_and(ours)
+    {
+        return;
+    }
+
+    // nothing we'd refuse ourselves gets passed on either
+    if !cfg
+        .filters
+        .permits(TrafficKind::of_opcode(frame.opcode), frame.source)
+    {
+        return;
+    }
+
+    let forwarded = match cfg
+        .relay
+        .as_mut()
+        .and_then(|r| r.forward(&frame, Instant::now()))
+    {
+        Some(forwarded) => forwarded,
+        None => return,
+    };
+
+    for (idx, link) in cfg.links.iter_mut().enumerate() {
+        if idx == via || link.paused {
+            continue;
+        }
+
+        if let Err(e) = link.channel.forward(&forwarded) {
+            send_event(events, Event::Error(e.to_string()));
+        }
+    }
+}
+
+fn handle_timer(cfg: &mut Config, events: &Sender<Event>, timer: Timer) {
+    match timer {
+        Timer::Advertise => {
+            advertise_all(cfg, events);
+            cfg.schedule_advertise();
+        }
+        Timer::LossReport => {
+            report_loss(cfg, events);
+            if cfg.receiving() {
+                cfg.schedule_loss_report();
+            }
+        }
+        Timer::Prune => {
+            let now = Instant::now();
+            for link in cfg.links.iter_mut() {
+                link.channel.prune(now);
+            }
+            if cfg.receiving() {
+                cfg.schedule_prune();
+            }
+        }
+        Timer::Retry => {
+            // paused links keep theirs until they're resumed
+            for link in cfg.links.iter_mut().filter(|l| !l.paused) {
+                for key in link.channel.interrupted() {
+                    link.channel.requeue(&key);
+                }
+            }
+        }
+        Timer::Shares => {
+            update_shares(cfg, events);
+            cfg.schedule_shares();
+        }
+        Timer::Carousel => {
+            turn_carousels(cfg, events);
+            if !cfg.carousels.is_empty() {
+                cfg.schedule_carousel();
+            }
+        }
+    }
+}
+
+fn handle_command(cfg: &mut Config, events: &Sender<Event>, command: Command) {
+    match command {
+        Command::Share(filepath, options) => share(cfg, events, filepath, options),
+        Command::Unshare(filepath) => {
+            // by path, the file may have changed or gone since it was shared
+            let hash = cfg
+                .sharing
+                .iter()
+                .find(|(_, share)| share.path == filepath)
+                .map(|(hash, _)| *hash);
+
+            if let Some(hash) = hash {
+                stop_share(cfg, events, hash);
+            }
+        }
+        Command::Carousel(filepath, duration) => start_carousel(cfg, events, filepath, duration),
+        Command::Download(hash, id) => {
+            let via = match cfg.shared.get(&hash).and_then(|s| cfg.link(&s.interface)) {
+                Some(via) => via,
+                None => {
+                    let error = String::from("unknown share");
+                    send_event(events, Event::DownloadFailed { id, error });
+                    return;
+                }
+            };
+
+            // requested once the link is back if it's down, carousels come round
+            // without being asked
+            cfg.requested.insert(hash, id);
+            if cfg.shared.get(&hash).is_some_and(|s| s.carousel) {
+                return;
+            }
+            if let Err(e) = cfg.request(via, hash) {
+                cfg.requested.remove(&hash);
+                let error = e.to_string();
+                send_event(events, Event::DownloadFailed { id, error });
+            }
+        }
+        Command::Cancel(id) => {
+            // a request that hasn't gone out yet stays home, anything already
+            // in flight is ignored once it arrives
+            let cancelled: Vec<_> = cfg
+                .requested
+                .iter()
+                .filter(|(_, requested)| **requested == id)
+                .map(|(hash, _)| *hash)
+                .collect();
+            for hash in cancelled {
+                cfg.requested.remove(&hash);
+                cfg.cancel_request(&hash);
+            }
+        }
+        Command::ChangeInterface(name) => {
+            if cfg.links.len() == 1 && cfg.link(&name).is_some() {
+                return;
+            }
+
+            let interface = match find_interface(events, &name) {
+                Some(interface) => interface,
+                None => return,
+            };
+
+            // shares don't follow us onto the new network
+            withdraw_shares(cfg, events);
+            match cfg.change_interface(interface) {
+                Ok(_) => discover(cfg, events),
+                Err(e) => send_event(events, Event::Error(e.to_string())),
+            };
+            send_event(events, Event::Interfaces(usable_interfaces()));
+        }
+        Command::AddInterface(name) => {
+            if cfg.link(&name).is_some() {
+                return;
+            }
+
+            let interface = match find_interface(events, &name) {
+                Some(interface) => interface,
+                None => return,
+            };
+
+            match cfg.add_interface(interface) {
+                Ok(_) => {
+                    // say hello and tell the new segment what we have
+                    let via = cfg.links.len() - 1;
+                    if let Err(e) = cfg.send_on(via, Payload::Discover) {
+                        send_event(events, Event::Error(e.to_string()));
+                    }
+                    advertise_on(cfg, events, via);
+                }
+                Err(e) => send_event(events, Event::Error(e.to_string())),
+            };
+            send_event(events, Event::Interfaces(usable_interfaces()));
+        }
+        Command::RemoveInterface(name) => {
+            let via = match cfg.link(&name) {
+                Some(via) => via,
+                None => return,
+            };
+
+            if cfg.links.len() == 1 {
+                let error = String::from("can't remove the last interface");
+                send_event(events, Event::Error(error));
+                return;
+            }
+
+            // withdraw our shares from that segment before leaving it
+            let shares: Vec<_> = cfg.sharing.keys().copied().collect();
+            for hash in shares {
+                cfg.send_on(via, Payload::Unshare(hash)).ok();
+            }
+
+            let withdrawn: Vec<_> = cfg
+                .shared
+                .values()
+                .filter(|s| s.interface == name)
+                .map(|s| s.id)
+                .collect();
+
+            cfg.remove_interface(&name);
+            for id in withdrawn {
+                send_event(events, Event::ShareWithdrawn(id));
+            }
+            send_event(events, Event::Interfaces(usable_interfaces()));
+        }
+        Command::SetDownloadDir(path) => {
+            if !path.is_dir() {
+                send_event(events, Event::Error(String::from("invalid path")));
+                return;
+            };
+
+            cfg.download_dir = path;
+        }
+        Command::SetAdvertiseInterval(interval) => {
+            if interval.is_zero() {
+                send_event(events, Event::Error(String::from("invalid interval")));
+                return;
+            }
+
+            cfg.advertise_interval = interval;
+            cfg.schedule_advertise();
+        }
+        Command::RequestCatalog(peer) => {
+            let via = match cfg.peers.get(&peer).and_then(|i| cfg.link(i)) {
+                Some(via) if !cfg.links[via].paused => via,
+                Some(_) => {
+                    send_event(events, Event::Error(String::from("link is down")));
+                    return;
+                }
+                None => {
+                    send_event(events, Event::Error(String::from("unknown peer")));
+                    return;
+                }
+            };
+
+            cfg.catalogs.insert(peer, vec![]);
+            match cfg.send_on(via, Payload::CatalogRequest(peer)) {
+                Ok(_) => (),
+                Err(e) => send_event(events, Event::Error(e.to_string())),
+            };
+        }
+        Command::AddFilter(rule) => cfg.filters.add(rule),
+        Command::RemoveFilter(rule) => cfg.filters.remove(&rule),
+        Command::SetVlan(vlan) => cfg.set_vlan(vlan),
+        Command::SetRelay(relay) => cfg.relay = relay.map(Relay::new),
+        Command::Record(None) => {
+            for link in cfg.links.iter_mut() {
+                link.channel.stop_recording();
+            }
+        }
+        Command::Record(Some(path)) => {
+            // one capture per interface when sharing on several
+            let single = cfg.links.len() == 1;
+            for link in cfg.links.iter_mut() {
+                let path = match single {
+                    true => path.clone(),
+                    false => capture_path(&path, &link.channel.interface_name()),
+                };
+
+                match link.channel.record(&path) {
+                    Ok(_) => send_event(
+                        events,
+                        Event::Notice(format!("recording to {0}", path.display())),
+                    ),
+                    Err(e) => send_event(events, Event::Error(e.to_string())),
+                };
+            }
+        }
+        Command::RemoteShares(reply) => {
+            reply.send(cfg.shared.values().cloned().collect()).ok();
+        }
+        Command::Push(filepath) => {
+            let data = match files::read_share(&filepath) {
+                Ok(data) => data,
+                Err(e) => {
+                    send_event(events, Event::Error(e.to_string()));
+                    return;
+                }
+            };
+
+            // only the name goes with it, receivers pick where it's saved
+            let name = Path::new(&filepath)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or(filepath);
+
+            if let Err(e) = cfg.broadcast(&Payload::Push(name, data)) {
+                send_event(events, Event::Error(e.to_string()));
+            }
+        }
+        Command::SendSnippet(text) => {
+            if let Err(e) = cfg.broadcast(&Payload::Snippet(text)) {
+                send_event(events, Event::Error(e.to_string()));
+            }
+        }
+        Command::Snippets(reply) => {
+            reply.send(cfg.snippets.iter().cloned().collect()).ok();
+        }
+        Command::AcceptPush(id) => {
+            if let Some(push) = cfg.pushes.take(id) {
+                save_push(cfg, events, &push.name, &push.data);
+            }
+        }
+        Command::RejectPush(id) => {
+            cfg.pushes.take(id);
+        }
+        Command::SetPushPolicy(policy) => cfg.pushes.policy = policy,
+        Command::Trust(peer) => {
+            cfg.pushes.trusted.insert(peer);
+        }
+        Command::Untrust(peer) => {
+            cfg.pushes.trusted.remove(&peer);
+        }
+        Command::PushSettings(reply) => {
+            let trusted = cfg.pushes.trusted.iter().copied().collect();
+            reply.send((cfg.pushes.policy, trusted)).ok();
+        }
+        Command::LocalShares(reply) => {
+            reply.send(cfg.sharing.values().cloned().collect()).ok();
+        }
+        Command::Peers(reply) => {
+            reply.send(cfg.peers.keys().copied().collect()).ok();
+        }
+        Command::Filters(reply) => {
+            reply.send(cfg.filters.rules()).ok();
+        }
+        Command::ActiveInterfaces(reply) => {
+            let names = cfg.links.iter().map(|l| l.channel.interface_name());
+            reply.send(names.collect()).ok();
+        }
+        // handled by the worker loop, it has to own the config to close it
+        Command::Shutdown(_) => (),
+    }
+}
+
+fn handle_payload(
+    cfg: &mut Config,
+    events: &Sender<Event>,
+    via: usize,
+    source: MacAddr,
+    packet: Payload,
+) {
+    match packet {
+        Payload::File(filehash, data) => {
+            let id = match cfg.requested.remove(&filehash) {
+                Some(id) => id,
+                None => return,
+            };
+
+            if let Some(share) = cfg.shared.get(&filehash) {
+                match files::save_download(&cfg.download_dir, &share.name, &data) {
+                    Ok(path) => send_event(events, Event::DownloadComplete { id, path }),
+                    Err(e) => {
+                        let error = e.to_string();
+                        send_event(events, Event::DownloadFailed { id, error })
+                    }
+                }
+            }
+        }
+        Payload::Advertise(filepath) => {
+            let hash = match compute_filehash(&filepath) {
+                Ok(hash) => hash,
+                Err(_) => return,
+            };
+
+            if cfg.sharing.contains_key(&hash) {
+                return;
+            }
+
+            // a carousel that ended is a plain share again, anyone waiting on
+            // it has to ask now
+            if let Some(share) = cfg.shared.get_mut(&hash) {
+                if share.peer == source && share.carousel {
+                    share.carousel = false;
+                    if cfg.requested.contains_key(&hash) {
+                        cfg.request(via, hash).ok();
+                    }
+                }
+                return;
+            }
+
+            let share = RemoteShare {
+                id: hash,
+                name: filepath,
+                peer: source,
+                interface: cfg.links[via].channel.interface_name(),
+                carousel: false,
+            };
+            cfg.shared.insert(hash, share.clone());
+            send_event(events, Event::ShareDiscovered(share));
+        }
+        Payload::Carousel(filepath) => {
+            let hash = match compute_filehash(&filepath) {
+                Ok(hash) => hash,
+                Err(_) => return,
+            };
+
+            if cfg.sharing.contains_key(&hash) {
+                return;
+            }
+
+            if let Some(share) = cfg.shared.get_mut(&hash) {
+                share.carousel |= share.peer == source;
+                return;
+            }
+
+            let share = RemoteShare {
+                id: hash,
+                name: filepath,
+                peer: source,
+                interface: cfg.links[via].channel.interface_name(),
+                carousel: true,
+            };
+            cfg.shared.insert(hash, share.clone());
+            send_event(events, Event::ShareDiscovered(share));
+        }
+        Payload::DownloadRequest(hash) => {
+            let now = Instant::now();
+            let share = match cfg.sharing.get_mut(&hash) {
+                Some(share) if share.live(now) && !share.exhausted(now) => share,
+                _ => return,
+            };
+
+            let data = match files::read_share(&share.path) {
+                Ok(data) => data,
+                Err(e) => {
+                    send_event(events, Event::Error(e.to_string()));
+                    return;
+                }
+            };
+
+            // the last download allowed still goes out, the share is withdrawn behind it
+            share.downloads += 1;
+            let exhausted = share.exhausted(now);
+
+            // answer on the segment that asked
+            match cfg.send_on(via, Payload::File(hash, data)) {
+                Ok(_) => (),
+                Err(e) => send_event(events, Event::Error(e.to_string())),
+            };
+
+            if exhausted {
+                stop_share(cfg, events, hash);
+            }
+        }
+        Payload::Discover => cfg.schedule_discover_reply(),
+        Payload::CatalogRequest(target) => {
+            if target == cfg.links[via].channel.mac_addr() {
+                send_catalog(cfg, events, via);
+            }
+        }
+        Payload::Catalog(page, pages, entries) => {
+            let catalog = match cfg.catalogs.get_mut(&source) {
+                Some(catalog) => catalog,
+                None => return,
+            };
+
+            if page >= pages {
+                return;
+            }
+
+            // (re)size on first page or if the responder's catalog changed
+            if catalog.len() != pages as usize {
+                *catalog = vec![None; pages as usize];
+            }
+
+            catalog[page as usize] = Some(entries);
+            if catalog.iter().any(|p| p.is_none()) {
+                return;
+            }
+
+            let entries: Vec<CatalogEntry> = cfg
+                .catalogs
+                .remove(&source)
+                .unwrap_or_default()
+                .into_iter()
+                .flatten()
+                .flatten()
+                .collect();
+
+            // remember entries so downloads started from the catalog can be saved,
+            // and list the ones we hadn't heard advertised yet
+            let interface = cfg.links[via].channel.interface_name();
+            for entry in entries.iter() {
+                if let Ok(hash) = compute_filehash(&entry.name) {
+                    if !cfg.sharing.contains_key(&hash) {
+                        let carousel = cfg.shared.get(&hash).is_some_and(|s| s.carousel);
+                        let share = RemoteShare {
+                            id: hash,
+                            name: entry.name.clone(),
+                            peer: source,
+                            interface: interface.clone(),
+                            carousel,
+                        };
+                        if cfg.shared.insert(hash, share.clone()).is_none() {
+                            send_event(events, Event::ShareDiscovered(share));
+                        }
+                    }
+                }
+            }
+
+            let peer = source;
+            send_event(events, Event::Catalog { peer, entries });
+        }
+        Payload::Unshare(hash) => {
+            // only the peer that advertised a share can withdraw it
+            if cfg.shared.get(&hash).is_some_and(|s| s.peer == source) {
+                cfg.shared.remove(&hash);
+                send_event(events, Event::ShareWithdrawn(hash));
+            }
+        }
+        // streams run on their own channel, see cli::stream
+        Payload::StreamOffer(_)
+        | Payload::StreamRequest(_, _)
+        | Payload::StreamData(_, _, _)
+        | Payload::StreamEnd(_, _)
+        | Payload::StreamRefused(_, _) => (),
+        Payload::Snippet(text) => {
+            let snippet = Snippet { peer: source, text };
+            if cfg.snippets.len() >= MAX_SNIPPETS {
+                cfg.snippets.pop_front();
+            }
+            cfg.snippets.push_back(snippet.clone());
+            send_event(events, Event::SnippetReceived(snippet));
+        }
+        Payload::Push(name, data) => match cfg.pushes.decide(source) {
+            Decision::Ignore => (),
+            Decision::Accept => save_push(cfg, events, &name, &data),
+            Decision::Prompt => {
+                let size = data.len();
+                let id = cfg.pushes.offer(source, name.clone(), data);
+                send_event(
+                    events,
+                    Event::PushOffered {
+                        id,
+                        peer: source,
+                        name,
+                        size,
+                    },
+                );
+            }
+        },
+        Payload::LossReport(key, received, expected) => {
+            cfg.links[via].channel.report_loss(key, received, expected);
+        }
+    }
+}
+
+fn find_interface(events: &Sender<Event>, name: &str) -> Option<NetworkInterface> {
+    let interface = usable_interfaces().into_iter().find(|i| i.name == name);
+    if interface.is_none() {
+        send_event(
+            events,
+            Event::Error(String::from("interface not available")),
+        );
+    }
+
+    interface
+}
+
+fn capture_path(path: &Path, interface: &str) -> PathBuf {
+    // capture.pcapng -> capture-eth0.pcapng
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{0}-{1}", stem, interface);
+    if let Some(extension) = path.extension() {
+        name = format!("{0}.{1}", name, extension.to_string_lossy());
+    }
+
+    path.with_file_name(name)
+}
+
+fn handle_link_change(cfg: &mut Config, events: &Sender<Event>) {
+    let interfaces = usable_interfaces();
+    send_event(events, Event::Interfaces(interfaces.clone()));
+
+    let links: Vec<_> = cfg
+        .links
+        .iter()
+        .map(|l| (l.channel.interface_name(), l.paused))
+        .collect();
+
+    for change in link_changes(&links, &interfaces) {
+        match change {
+            LinkChange::Pause(via) => {
+                cfg.links[via].paused = true;
+                send_event(events, Event::Paused(links[via].0.clone()));
+            }
+            LinkChange::Resume(via, interface) => resume(cfg, events, via, interface),
+            LinkChange::FailOver(interface) => fail_over(cfg, events, interface),
+        }
+    }
+}
+
+fn resume(cfg: &mut Config, events: &Sender<Event>, via: usize, interface: NetworkInterface) {
+    // the old socket doesn't always survive the interface going away
+    let name = interface.name.clone();
+    if let Err(e) = cfg.reopen(via, interface) {
+        send_event(events, Event::Error(e.to_string()));
+        return;
+    }
+
+    send_event(events, Event::Resumed(name.clone()));
+
+    // transfers cut off when the link went down carry on from where they failed
+    let channel = &mut cfg.links[via].channel;
+    for key in channel.interrupted() {
+        channel.requeue(&key);
+    }
+
+    // catch up on everything held back while the link was down
+    if let Err(e) = cfg.send_on(via, Payload::Discover) {
+        send_event(events, Event::Error(e.to_string()));
+        return;
+    }
+    advertise_on(cfg, events, via);
+
+    let requested: Vec<_> = cfg
+        .requested
+        .keys()
+        .filter(|hash| {
+            cfg.shared
+                .get(*hash)
+                .is_some_and(|s| s.interface == name && !s.carousel)
+        })
+        .copied()
+        .collect();
+
+    for hash in requested {
+        if let Err(e) = cfg.request(via, hash) {
+            send_event(events, Event::Error(e.to_string()));
+            return;
+        }
+    }
+}
+
+fn fail_over(cfg: &mut Config, events: &Sender<Event>, interface: NetworkInterface) {
+    // peers and their shares belong to the old networks, ours come along
+    let name = interface.name.clone();
+    let sharing = std::mem::take(&mut cfg.sharing);
+    let requested = std::mem::take(&mut cfg.requested);
+    if let Err(e) = cfg.change_interface(interface) {
+        cfg.sharing = sharing;
+        cfg.requested = requested;
+        send_event(events, Event::Error(e.to_string()));
+        return;
+    }
+
+    cfg.sharing = sharing;
+    for (_, id) in requested {
+        let error = String::from("interface lost");
+        send_event(events, Event::DownloadFailed { id, error });
+    }
+
+    send_event(events, Event::FailedOver(name));
+    discover(cfg, events);
+    advertise_all(cfg, events);
+}
+
+fn shutdown(cfg: &mut Config, events: &Sender<Event>) {
+    // finish anything already received, completed files get written out
+    for via in 0..cfg.links.len() {
+        for data in cfg.links[via].channel.frames().try_iter() {
+            handle_frame(cfg, events, via, &data);
+        }
+    }
+
+    withdraw_shares(cfg, events);
+
+    // the withdrawals go out before the sockets close, transfers are dropped
+    for link in cfg.links.iter_mut() {
+        link.channel.discard_transfers();
+    }
+    while let Some(deadline) = next_send(cfg) {
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        pump(cfg, events, usize::MAX);
+    }
+}
+
+// paused links keep their queue until they come back
+fn next_send(cfg: &Config) -> Option<Instant> {
+    cfg.links
+        .iter()
+        .filter(|l| !l.paused)
+        .filter_map(|l| l.channel.next_send())
+        .min()
+}
+
+fn pump(cfg: &mut Config, events: &Sender<Event>, budget: usize) {
+    let mut failed = false;
+    for link in cfg.links.iter_mut().filter(|l| !l.paused) {
+        if let Err(e) = link.channel.pump(budget) {
+            send_event(events, Event::Error(e.to_string()));
+            failed = true;
+        }
+    }
+
+    // links that stay up don't get resumed, so try them again in a while
+    if failed {
+        cfg.schedule_retry();
+    }
+}
+
+fn withdraw_shares(cfg: &mut Config, events: &Sender<Event>) {
+    cfg.carousels.clear();
+    let shares: Vec<_> = cfg.sharing.drain().map(|(hash, _)| hash).collect();
+    for hash in shares {
+        if let Err(e) = cfg.broadcast(&Payload::Unshare(hash)) {
+            send_event(events, Event::Error(e.to_string()));
+            return;
+        }
+    }
+}
+
+fn report_loss(cfg: &mut Config, events: &Sender<Event>) {
+    // tell each sender how much of its transfers made it through, on the
+    // link they're arriving on
+    for via in 0..cfg.links.len() {
+        for report in cfg.links[via].channel.loss_reports() {
+            let packet = Payload::LossReport(report.key, report.received, report.expected);
+            if let Err(e) = cfg.send_on(via, packet) {
+                send_event(events, Event::Error(e.to_string()));
+            }
+        }
+    }
+}
+
+fn discover(cfg: &mut Config, events: &Sender<Event>) {
+    // ask peers already on the network to re-advertise their shares
+    if let Err(e) = cfg.broadcast(&Payload::Discover) {
+        send_event(events, Event::Error(e.to_string()));
+    }
+}
+
+fn advertise_all(cfg: &mut Config, events: &Sender<Event>) {
+    for via in 0..cfg.links.len() {
+        advertise_on(cfg, events, via);
+    }
+}
+
+fn advertise_on(cfg: &mut Config, events: &Sender<Event>, via: usize) {
+    let now = Instant::now();
+    let files: Vec<_> = cfg
+        .sharing
+        .iter()
+        .filter(|(_, share)| share.live(now))
+        .map(|(hash, share)| advertisement(cfg, hash, &share.path))
+        .collect();
+    for packet in files {
+        if let Err(e) = cfg.send_on(via, packet) {
+            send_event(events, Event::Error(e.to_string()));
+            return;
+        }
+    }
+}
+
+fn share(cfg: &mut Config, events: &Sender<Event>, filepath: String, options: ShareOptions) {
+    let hash = match compute_filehash(&filepath) {
+        Ok(hash) => hash,
+        Err(e) => {
+            send_event(events, Event::Error(e.to_string()));
+            return;
+        }
+    };
+
+    let share = match LocalShare::scheduled(filepath.clone(), &options, Instant::now()) {
+        Some(share) => share,
+        None => {
+            send_event(events, Event::Error(String::from("invalid schedule")));
+            return;
+        }
+    };
+    let live = share.live(Instant::now());
+    cfg.sharing.insert(hash, share);
+    cfg.schedule_shares();
+
+    // scheduled shares are announced by the timer once they start
+    if !live {
+        send_event(events, Event::Notice(format!("scheduled: {0}", filepath)));
+        return;
+    }
+
+    // links that are down get it once they come back
+    match cfg.broadcast(&Payload::Advertise(filepath.clone())) {
+        Ok(_) => send_event(events, Event::Sharing(filepath)),
+        Err(e) => send_event(events, Event::Error(e.to_string())),
+    };
+}
+
+fn update_shares(cfg: &mut Config, events: &Sender<Event>) {
+    let now = Instant::now();
+    let mut started = vec![];
+    for share in cfg.sharing.values_mut() {
+        if share.starts.is_some_and(|at| now >= at) {
+            share.starts = None;
+            started.push(share.path.clone());
+        }
+    }
+
+    for filepath in started {
+        match cfg.broadcast(&Payload::Advertise(filepath.clone())) {
+            Ok(_) => send_event(events, Event::Sharing(filepath)),
+            Err(e) => send_event(events, Event::Error(e.to_string())),
+        };
+    }
+
+    let stopped: Vec<_> = cfg
+        .sharing
+        .iter()
+        .filter(|(_, share)| share.exhausted(now))
+        .map(|(hash, _)| *hash)
+        .collect();
+
+    for hash in stopped {
+        stop_share(cfg, events, hash);
+    }
+}
+
+fn save_push(cfg: &Config, events: &Sender<Event>, name: &str, data: &[u8]) {
+    // same place downloads go, but a push never replaces a file already there
+    match files::save_new(&cfg.download_dir, name, data) {
+        Ok(path) => send_event(events, Event::PushSaved(path)),
+        Err(e) => send_event(events, Event::Error(e.to_string())),
+    }
+}
+
+fn stop_share(cfg: &mut Config, events: &Sender<Event>, hash: FileHash) {
+    cfg.carousels.remove(&hash);
+    let share = match cfg.sharing.remove(&hash) {
+        Some(share) => share,
+        None => return,
+    };
+
+    if let Err(e) = cfg.broadcast(&Payload::Unshare(hash)) {
+        send_event(events, Event::Error(e.to_string()));
+    }
+    send_event(events, Event::ShareStopped(share.path));
+}
+
+fn advertisement(cfg: &Config, hash: &FileHash, file: &str) -> Payload {
+    match cfg.carousels.contains_key(hash) {
+        true => Payload::Carousel(file.to_string()),
+        false => Payload::Advertise(file.to_string()),
+    }
+}
+
+fn start_carousel(cfg: &mut Config, events: &Sender<Event>, filepath: String, duration: Duration) {
+    let until = match Instant::now().checked_add(duration) {
+        Some(until) => until,
+        None => {
+            send_event(events, Event::Error(String::from("invalid duration")));
+            return;
+        }
+    };
+
+    let hash = match compute_filehash(&filepath) {
+        Ok(hash) => hash,
+        Err(e) => {
+            send_event(events, Event::Error(e.to_string()));
+            return;
+        }
+    };
+
+    let data = match files::read_share(&filepath) {
+        Ok(data) => Payload::File(hash, data).serialize(),
+        Err(e) => {
+            send_event(events, Event::Error(e.to_string()));
+            return;
+        }
+    };
+
+    let carousel = Carousel {
+        key: rand::random(),
+        data,
+        next: 0,
+        until,
+    };
+
+    if carousel.total() >= u16::MAX as usize {
+        send_event(
+            events,
+            Event::Error(FloodFileError::FileTooLarge.to_string()),
+        );
+        return;
+    }
+
+    // it's a share as well, so peers that missed the carousel can still ask
+    cfg.sharing.insert(hash, LocalShare::new(filepath.clone()));
+    cfg.carousels.insert(hash, carousel);
+    cfg.schedule_carousel();
+
+    match cfg.broadcast(&Payload::Carousel(filepath.clone())) {
+        Ok(_) => send_event(events, Event::Sharing(filepath)),
+        Err(e) => send_event(events, Event::Error(e.to_string())),
+    };
+}
+
+fn turn_carousels(cfg: &mut Config, events: &Sender<Event>) {
+    let now = Instant::now();
+    let ended: Vec<_> = cfg
+        .carousels
+        .iter()
+        .filter(|(_, c)| now >= c.until)
+        .map(|(hash, _)| *hash)
+        .collect();
+
+    // peers tuned in fall back to asking once they see the plain advertisement
+    for hash in ended {
+        cfg.carousels.remove(&hash);
+        if let Some(file) = cfg.sharing.get(&hash).map(|s| s.path.clone()) {
+            cfg.broadcast(&Payload::Advertise(file.clone())).ok();
+            send_event(events, Event::CarouselEnded(file));
+        }
+    }
+
+    let mut rounds = vec![];
+    for (hash, carousel) in cfg.carousels.iter_mut() {
+        let total = carousel.total();
+
+        // links still working through the last burst sit this one out, so a slow
+        // segment doesn't build up a backlog
+        let ready: Vec<bool> = cfg
+            .links
+            .iter()
+            .map(|l| !l.paused && !l.channel.queued(&carousel.key))
+            .collect();
+
+        for _ in 0..CAROUSEL_BURST.min(total) {
+            let offset = carousel.next;
+            let chunk = carousel
+                .data
+                .chunks(CHUNK_SIZE)
+                .nth(offset)
+                .unwrap_or_default();
+
+            for (link, _) in cfg.links.iter_mut().zip(&ready).filter(|(_, r)| **r) {
+                let queued = link.channel.queue_chunk(
+                    Payload::FILE_OPCODE,
+                    offset as u16,
+                    total as u16,
+                    carousel.key,
+                    chunk,
+                    Priority::Background,
+                );
+
+                if let Err(e) = queued {
+                    send_event(events, Event::Error(e.to_string()));
+                }
+            }
+
+            carousel.next = (offset + 1) % total;
+            if carousel.next == 0 {
+                rounds.push(*hash);
+            }
+        }
+    }
+
+    // re-announced every round for anyone who tuned in since
+    for hash in rounds {
+        if let Some(file) = cfg.sharing.get(&hash).map(|s| s.path.clone()) {
+            cfg.broadcast(&Payload::Carousel(file)).ok();
+        }
+    }
+}
+
+fn send_catalog(cfg: &mut Config, events: &Sender<Event>, via: usize) {
+    let now = Instant::now();
+    let entries: Vec<CatalogEntry> = cfg
+        .sharing
+        .values()
+        .filter(|share| share.live(now))
+        .map(|share| CatalogEntry {
+            name: share.path.clone(),
+            size: files::share_size(&share.path),
+        })
+        .collect();
+
+    // always respond with at least one (possibly empty) page
+    let pages: Vec<&[CatalogEntry]> = match entries.is_empty() {
+        true => vec![&[]],
+        false => entries.chunks(CATALOG_PAGE_SIZE).collect(),
+    };
+
+    let total = pages.len() as u16;
+    for (page, entries) in pages.into_iter().enumerate() {
+        let payload = Payload::Catalog(page as u16, total, entries.to_vec());
+        if let Err(e) = cfg.send_on(via, payload) {
+            send_event(events, Event::Error(e.to_string()));
+            return;
+        }
+    }
+}
+
+fn send_event(tx: &Sender<Event>, event: Event) {
+    // nobody listening is fine, embedders may not care about events. a full
+    // backlog means nobody is reading, the network doesn't wait for them.
+    if let Err(TrySendError::Full(event)) = tx.try_send(event) {
+        log::warn!("event backlog full, dropped {event:?}");
+    }
+}
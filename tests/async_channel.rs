@@ -0,0 +1,32 @@
+#![cfg(feature = "async")]
+
+use floodfile::network::async_channel::AsyncChannel;
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::Payload;
+use pnet::util::MacAddr;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+
+#[tokio::test]
+async fn streams_payloads_from_peer() {
+    let bus = Loopback::new();
+    let sender_mac = MacAddr::new(2, 0, 0, 0, 0, 1);
+
+    let mut sender = AsyncChannel::from(bus.channel(sender_mac));
+    let mut peer = AsyncChannel::from(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2)));
+
+    let data = vec![7u8; 4096];
+    sender
+        .send(Payload::File([1; 16], data.clone()))
+        .await
+        .unwrap();
+
+    let received = tokio::time::timeout(Duration::from_secs(5), peer.next())
+        .await
+        .expect("timed out waiting for payload")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(received.0, sender_mac);
+    assert!(matches!(received.1, Payload::File(hash, d) if hash == [1; 16] && d == data));
+}
@@ -0,0 +1,117 @@
+use clap::Parser;
+use floodfile::cli::{self, Cli, CliCommand};
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::Payload;
+use floodfile::network::utils::compute_filehash;
+use floodfile::session::{Event, Session};
+use pnet::util::MacAddr;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[test]
+fn no_subcommand_starts_the_ui() {
+    let cli = Cli::try_parse_from(["floodfile"]).unwrap();
+    assert!(cli.command.is_none());
+}
+
+#[test]
+fn parses_subcommands() {
+    let cli = Cli::try_parse_from([
+        "floodfile",
+        "get",
+        "00112233445566778899aabbccddeeff",
+        "-o",
+        "/tmp",
+        "--json",
+    ])
+    .unwrap();
+    assert!(matches!(cli.command, Some(CliCommand::Get { ref id, .. }) if id.len() == 32));
+
+    let cli = Cli::try_parse_from(["floodfile", "share", "notes.txt", "--iface", "eth0"]).unwrap();
+    assert!(matches!(cli.command, Some(CliCommand::Share { .. })));
+
+    let cli = Cli::try_parse_from(["floodfile", "send", "-", "--wait", "10"]).unwrap();
+    assert!(
+        matches!(cli.command, Some(CliCommand::Send { ref input, wait: 10, .. }) if input == "-")
+    );
+
+    assert!(Cli::try_parse_from(["floodfile", "get"]).is_err());
+    assert!(Cli::try_parse_from(["floodfile", "recv"]).is_err());
+}
+
+#[test]
+fn parses_ids() {
+    let id = cli::parse_id("00112233445566778899aabbccddeeFF").unwrap();
+    assert_eq!(id[0], 0x00);
+    assert_eq!(id[15], 0xff);
+
+    assert!(cli::parse_id("00112233445566778899aabbccddee").is_none());
+    assert!(cli::parse_id("00112233445566778899aabbccddeeffaa").is_none());
+    assert!(cli::parse_id("0011223344556677889gaabbccddeeff").is_none());
+    assert!(cli::parse_id("+0112233445566778899aabbccddeeff").is_none());
+    assert!(cli::parse_id("ü0112233445566778899aabbccddeef").is_none());
+}
+
+fn command(args: &[&str]) -> CliCommand {
+    let args = ["floodfile"].iter().chain(args).copied();
+    Cli::try_parse_from(args).unwrap().command.unwrap()
+}
+
+#[test]
+fn get_exits_with_not_found_and_timeout() {
+    let bus = Loopback::new();
+    let session = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2))).unwrap();
+
+    let missing = "00112233445566778899aabbccddeeff";
+    let code = cli::run_on(&session, command(&["get", missing, "--timeout", "0"]));
+    assert_eq!(code, ExitCode::from(cli::EXIT_NOT_FOUND));
+
+    // advertised, but nobody answers the download request
+    let mut silent = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    silent
+        .send(Payload::Advertise(String::from("/tmp/ghost.bin")))
+        .unwrap();
+    loop {
+        match session.events().recv_timeout(Duration::from_secs(5)) {
+            Ok(Event::ShareDiscovered(_)) => break,
+            Ok(_) => (),
+            Err(e) => panic!("{0}", e),
+        }
+    }
+
+    let id = compute_filehash("/tmp/ghost.bin").unwrap();
+    let id: String = id.iter().map(|b| format!("{:02x}", b)).collect();
+    let output = std::env::temp_dir();
+    let code = cli::run_on(
+        &session,
+        command(&["get", &id, "-o", output.to_str().unwrap(), "--timeout", "1"]),
+    );
+    assert_eq!(code, ExitCode::from(cli::EXIT_TIMEOUT));
+
+    let code = cli::run_on(&session, command(&["get", "not-an-id"]));
+    assert_eq!(code, ExitCode::from(cli::EXIT_USAGE));
+    let code = cli::run_on(
+        &session,
+        command(&["share", "x", "--minutes", "307445734561825861"]),
+    );
+    assert_eq!(code, ExitCode::from(cli::EXIT_USAGE));
+
+    // waits too long to set a deadline for
+    let forever = u64::MAX.to_string();
+    let code = cli::run_on(&session, command(&["list", "--wait", &forever]));
+    assert_eq!(code, ExitCode::from(cli::EXIT_USAGE));
+    let code = cli::run_on(
+        &session,
+        command(&[
+            "get",
+            &id,
+            "-o",
+            output.to_str().unwrap(),
+            "--timeout",
+            &forever,
+        ]),
+    );
+    assert_eq!(code, ExitCode::from(cli::EXIT_USAGE));
+
+    session.shutdown().ok();
+}
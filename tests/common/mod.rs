@@ -0,0 +1,17 @@
+// helpers shared by the integration tests, each test crate uses a few of them
+#![allow(dead_code)]
+
+use floodfile::network::payload::Payload;
+use floodfile::network::Channel;
+use pnet::util::MacAddr;
+
+// every payload waiting on the channel, without blocking
+pub fn drain(channel: &mut Channel) -> Vec<(MacAddr, Payload)> {
+    let mut received = vec![];
+    for _ in 0..64 {
+        if let Ok(Some(packet)) = channel.recv() {
+            received.push(packet);
+        }
+    }
+    received
+}
@@ -0,0 +1,109 @@
+use floodfile::network::filter::{load_rules, save_rules, FilterRule, Filters, Rule, TrafficKind};
+use floodfile::network::payload::Payload;
+use pnet::util::MacAddr;
+
+const PEER: MacAddr = MacAddr(2, 0, 0, 0, 0, 1);
+const OTHER: MacAddr = MacAddr(2, 0, 0, 0, 0, 2);
+
+fn rule(rule: Rule, kind: TrafficKind, mac: MacAddr) -> FilterRule {
+    FilterRule { rule, kind, mac }
+}
+
+#[test]
+fn deny_wins_over_allow_for_the_same_peer() {
+    let mut filters = Filters::default();
+    assert!(filters.permits(TrafficKind::File, PEER));
+
+    filters.add(rule(Rule::Allow, TrafficKind::File, PEER));
+    filters.add(rule(Rule::Deny, TrafficKind::File, PEER));
+    assert!(!filters.permits(TrafficKind::File, PEER));
+
+    // other kinds are untouched
+    assert!(filters.permits(TrafficKind::Advertise, PEER));
+}
+
+#[test]
+fn allow_rules_turn_a_kind_into_an_allow_list() {
+    let mut filters = Filters::default();
+    filters.add(rule(Rule::Allow, TrafficKind::Request, PEER));
+
+    assert!(filters.permits(TrafficKind::Request, PEER));
+    assert!(!filters.permits(TrafficKind::Request, OTHER));
+
+    filters.remove(&rule(Rule::Allow, TrafficKind::Request, PEER));
+    assert!(filters.permits(TrafficKind::Request, OTHER));
+}
+
+#[test]
+fn rules_parse_and_print_the_same_way() {
+    let parsed: FilterRule = "deny  advertise 02:00:00:00:00:01".parse().unwrap();
+    assert_eq!(parsed, rule(Rule::Deny, TrafficKind::Advertise, PEER));
+    assert_eq!(parsed.to_string(), "deny advertise 02:00:00:00:00:01");
+    assert_eq!(parsed.to_string().parse::<FilterRule>().unwrap(), parsed);
+
+    for invalid in [
+        "",
+        "deny advertise",
+        "block file 02:00:00:00:00:01",
+        "allow files 02:00:00:00:00:01",
+        "allow file 02:00:00:00:01",
+        "allow file 02:00:00:00:00:01 extra",
+    ] {
+        assert!(invalid.parse::<FilterRule>().is_err(), "{0}", invalid);
+    }
+}
+
+#[test]
+fn every_payload_has_a_kind() {
+    use TrafficKind::{Advertise, File, Request};
+    let payloads = [
+        (Payload::File([0; 16], vec![]), File),
+        (Payload::Advertise(String::from("a")), Advertise),
+        (Payload::DownloadRequest([0; 16]), Request),
+        (Payload::Discover, Request),
+        (Payload::CatalogRequest(PEER), Request),
+        (Payload::Catalog(0, 1, vec![]), Advertise),
+        (Payload::Unshare([0; 16]), Advertise),
+        (Payload::LossReport([0; 8], 1, 2), Request),
+        (Payload::Carousel(String::from("a")), Advertise),
+        (Payload::Push(String::from("a"), vec![]), File),
+        (Payload::Snippet(String::from("a")), File),
+        (Payload::StreamOffer([0; 16]), Advertise),
+        (Payload::StreamRequest([0; 16], 0), Request),
+        (Payload::StreamData([0; 16], 0, vec![]), File),
+        (Payload::StreamEnd([0; 16], 0), File),
+        (Payload::StreamRefused([0; 16], PEER), File),
+    ];
+
+    for (payload, kind) in payloads {
+        assert_eq!(
+            TrafficKind::of(&payload),
+            kind,
+            "{0}",
+            Payload::kind(payload.opcode())
+        );
+    }
+
+    // relayed chunks of something newer than us
+    assert_eq!(TrafficKind::of_opcode(200), File);
+}
+
+#[test]
+fn rules_are_saved_and_loaded_again() {
+    let dir = std::env::temp_dir().join(format!("floodfile-filters-{0}", std::process::id()));
+    let path = dir.join("nested").join("filters");
+
+    // nothing saved yet is no rules
+    assert!(load_rules(&path).unwrap().is_empty());
+
+    let rules = vec![
+        rule(Rule::Deny, TrafficKind::Advertise, PEER),
+        rule(Rule::Allow, TrafficKind::File, PEER),
+    ];
+    save_rules(&path, &rules).unwrap();
+    assert_eq!(load_rules(&path).unwrap(), rules);
+
+    std::fs::write(&path, "deny advertise nobody\n").unwrap();
+    assert!(load_rules(&path).is_err());
+    std::fs::remove_dir_all(&dir).ok();
+}
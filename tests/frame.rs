@@ -0,0 +1,123 @@
+mod common;
+
+use floodfile::network::frame::{Frame, CHUNK_SIZE, PROTOCOL_VERSION};
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::Payload;
+use pnet::util::MacAddr;
+use std::fs;
+use std::process::Command;
+
+use common::drain;
+
+fn frame() -> Frame {
+    Frame {
+        source: MacAddr::new(2, 0, 0, 0, 0, 1),
+        relayed_by: Some(MacAddr::new(2, 0, 0, 0, 0, 9)),
+        vlan: None,
+        opcode: 9,
+        hops: 3,
+        offset: 0x0102,
+        total: 0x0304,
+        key: [5; 8],
+        nonce: [6; 4],
+        chunk: vec![0xaa; 10],
+    }
+}
+
+#[test]
+fn lays_out_the_header_in_the_arp_payload() {
+    let data = frame().encode().unwrap();
+
+    // the relay is the ethernet sender, the original host the arp sender
+    assert_eq!(data[6..12], [2, 0, 0, 0, 0, 9]);
+    assert_eq!(data[12..14], [0x08, 0x06]);
+    assert_eq!(data[22..28], [2, 0, 0, 0, 0, 1]);
+
+    // preamble, version, opcode, hops, then little endian offset and total
+    let len = data[19] as usize;
+    assert_eq!(len, 23 + 10);
+    assert_eq!(&data[28..32], b"flod");
+    assert_eq!(
+        data[32..39],
+        [PROTOCOL_VERSION, 9, 3, 0x02, 0x01, 0x04, 0x03]
+    );
+    assert_eq!(data[39..47], [5; 8]);
+    assert_eq!(data[47..51], [6; 4]);
+    assert_eq!(data[51..61], [0xaa; 10]);
+
+    // the payload is repeated after the empty target mac
+    assert_eq!(data[61..67], [0; 6]);
+    assert_eq!(data[67..67 + len], data[28..28 + len]);
+    assert_eq!(data.len(), 67 + len);
+}
+
+#[test]
+fn decodes_padded_frames_and_rejects_others() {
+    let sent = frame();
+    let mut data = sent.encode().unwrap();
+    let len = data.len();
+
+    // drivers pad short frames, the length on the wire excludes that
+    data.extend([0; 16]);
+    assert_eq!(Frame::decode(&data), Some((sent.clone(), len)));
+
+    assert!(Frame::decode(&data[..len - 1]).is_none());
+
+    // 1.0.0 frames, and frames from versions we don't know
+    let mut other = data.clone();
+    other[28..32].copy_from_slice(b"file");
+    assert!(Frame::decode(&other).is_none());
+
+    for version in [0, 1, PROTOCOL_VERSION + 1] {
+        let mut other = data.clone();
+        other[32] = version;
+        assert!(Frame::decode(&other).is_none());
+    }
+
+    // a chunk too large to fit the one byte length field
+    let oversized = Frame {
+        chunk: vec![0; CHUNK_SIZE + 1],
+        ..sent
+    };
+    assert!(oversized.encode().is_err());
+}
+
+#[test]
+fn decodes_and_exports_a_capture() {
+    let dir = std::env::temp_dir().join(format!("floodfile-decode-{0}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let capture = dir.join("transfer.pcapng");
+
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut receiver = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    let data: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+    receiver.record(&capture).unwrap();
+    sender.send(Payload::File([7; 16], data.clone())).unwrap();
+    assert_eq!(drain(&mut receiver).len(), 1);
+    receiver.stop_recording();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_floodfile-decode"))
+        .arg(&capture)
+        .arg("--export")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // one line per frame, the last one completing the file
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().any(|l| l.contains("op=0(file)")));
+    assert!(stdout.contains(&format!("complete: file {0} (4096 bytes)", "07".repeat(16))));
+
+    let exported = fs::read(dir.join(format!("{0}.bin", "07".repeat(16)))).unwrap();
+    fs::remove_dir_all(&dir).ok();
+    assert_eq!(exported, data);
+
+    // no capture to read is a usage error
+    let usage = Command::new(env!("CARGO_BIN_EXE_floodfile-decode"))
+        .output()
+        .unwrap();
+    assert_eq!(usage.status.code(), Some(2));
+}
@@ -0,0 +1,217 @@
+mod common;
+
+use floodfile::errors::FloodFileError;
+use floodfile::network::frame::CHUNK_SIZE;
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::Payload;
+use floodfile::network::scheduler::Priority;
+use pnet::util::MacAddr;
+use std::time::Duration;
+
+use common::drain;
+
+#[test]
+fn drops_own_echoed_frames() {
+    let bus = Loopback::new();
+    let mac = MacAddr::new(2, 0, 0, 0, 0, 1);
+
+    let mut sender = bus.channel(mac);
+    let mut same_host = bus.channel(mac);
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    sender
+        .send(Payload::Advertise(String::from("/tmp/notes.txt")))
+        .unwrap();
+
+    // the sender sees its own echo on the bus, but must not process it
+    assert!(drain(&mut sender).is_empty());
+
+    // another instance with the same mac still receives it thanks to the nonce
+    let received = drain(&mut same_host);
+    assert_eq!(received.len(), 1);
+    assert!(
+        matches!(&received[0], (src, Payload::Advertise(p)) if *src == mac && p == "/tmp/notes.txt")
+    );
+
+    assert_eq!(drain(&mut peer).len(), 1);
+}
+
+#[test]
+fn delivers_unshare_notices() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    sender.send(Payload::Unshare([9; 16])).unwrap();
+
+    let received = drain(&mut peer);
+    assert_eq!(received.len(), 1);
+    assert!(matches!(received[0].1, Payload::Unshare(hash) if hash == [9; 16]));
+}
+
+#[test]
+fn queued_payloads_wait_for_pump() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    sender.queue(Payload::Discover).unwrap();
+    assert!(sender.pending());
+    assert!(drain(&mut peer).is_empty());
+
+    assert_eq!(sender.pump(16).unwrap(), 1);
+    assert!(!sender.pending());
+    assert!(matches!(drain(&mut peer)[..], [(_, Payload::Discover)]));
+}
+
+#[test]
+fn resumes_a_transfer_from_its_offset() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    let path = "x".repeat(CHUNK_SIZE + 10);
+    let packet = Payload::Advertise(path.clone());
+    let data = packet.serialize();
+    let key = [7; 8];
+
+    // the first chunk made it out before the transfer was interrupted
+    sender
+        .send_chunk(packet.opcode(), 0, 2, key, &data[..CHUNK_SIZE])
+        .unwrap();
+    assert!(drain(&mut peer).is_empty());
+
+    sender.resume(packet, key, 1).unwrap();
+    let received = drain(&mut peer);
+    assert!(matches!(&received[..], [(_, Payload::Advertise(p))] if *p == path));
+}
+
+#[test]
+fn requeues_an_interrupted_transfer_from_the_failed_chunk() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    let path = "x".repeat(CHUNK_SIZE + 10);
+    let key = [7; 8];
+    sender
+        .queue_with_key(Payload::Advertise(path.clone()), key)
+        .unwrap();
+    assert_eq!(sender.pump(1).unwrap(), 1);
+
+    bus.set_down(true);
+    let result = loop {
+        std::thread::sleep(Duration::from_millis(1));
+        match sender.pump(1) {
+            Ok(0) => continue,
+            result => break result,
+        }
+    };
+    assert!(matches!(
+        result,
+        Err(FloodFileError::TransferInterrupted { key: k, offset: 1, .. }) if k == key
+    ));
+    assert!(!sender.pending());
+    assert_eq!(sender.interrupted(), vec![key]);
+
+    bus.set_down(false);
+    assert!(sender.requeue(&key));
+    while sender.pending() {
+        std::thread::sleep(Duration::from_millis(1));
+        sender.pump(16).unwrap();
+    }
+
+    let received = drain(&mut peer);
+    assert!(matches!(&received[..], [(_, Payload::Advertise(p))] if *p == path));
+}
+
+#[test]
+fn reports_chunks_missing_since_the_last_report() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+    let key = [3; 8];
+
+    // chunk 1 of 4 never makes it
+    for offset in [0, 2] {
+        sender.send_chunk(1, offset, 4, key, b"chunk").unwrap();
+    }
+    drain(&mut peer);
+
+    let reports = peer.loss_reports();
+    assert!(matches!(&reports[..], [r] if r.key == key && (r.received, r.expected) == (2, 3)));
+    assert!(peer.loss_reports().is_empty());
+}
+
+#[test]
+fn late_joiners_complete_a_carousel_on_the_next_round() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    let contents: Vec<u8> = (0..2048).map(|i| (i * 7 % 251) as u8).collect();
+    let data = Payload::File([5; 16], contents.clone()).serialize();
+    let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+    let total = chunks.len() as u16;
+    let key = [4; 8];
+
+    // tuned in halfway through the first round, the start comes round again
+    let half = chunks.len() / 2;
+    let order = (half..chunks.len()).chain(0..half);
+    for offset in order {
+        sender
+            .queue_chunk(
+                Payload::FILE_OPCODE,
+                offset as u16,
+                total,
+                key,
+                chunks[offset],
+                Priority::Background,
+            )
+            .unwrap();
+    }
+    // paced, so it takes a few goes
+    while sender.pending() {
+        sender.pump(usize::MAX).unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let received = drain(&mut peer);
+    assert!(
+        matches!(&received[..], [(_, Payload::File(hash, file))] if *hash == [5; 16] && *file == contents)
+    );
+}
+
+#[test]
+fn delivers_pushed_files() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    let contents: Vec<u8> = (0..4096).map(|i| (i % 13) as u8).collect();
+    sender
+        .send(Payload::Push(String::from("build.log"), contents.clone()))
+        .unwrap();
+
+    let received = drain(&mut peer);
+    assert!(
+        matches!(&received[..], [(_, Payload::Push(name, file))] if name == "build.log" && *file == contents)
+    );
+}
+
+#[test]
+fn delivers_snippets_within_the_limit() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    let command = String::from("cargo build --release 2>&1 | tee build.log");
+    sender.send(Payload::Snippet(command.clone())).unwrap();
+
+    // oversized snippets are dropped on arrival
+    let oversized = "x".repeat(Payload::MAX_SNIPPET_LEN + 1);
+    sender.send(Payload::Snippet(oversized)).unwrap();
+
+    let received = drain(&mut peer);
+    assert!(matches!(&received[..], [(_, Payload::Snippet(text))] if *text == command));
+}
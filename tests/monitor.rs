@@ -0,0 +1,74 @@
+use floodfile::network::monitor::{link_changes, LinkChange};
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+
+// IFF_UP and IFF_RUNNING
+const UP: u32 = 0x1 | 0x40;
+
+fn interface(name: &str, index: u32, flags: u32) -> NetworkInterface {
+    NetworkInterface {
+        name: name.to_string(),
+        description: String::new(),
+        index,
+        mac: Some(MacAddr::new(2, 0, 0, 0, 0, index as u8)),
+        ips: vec![],
+        flags,
+    }
+}
+
+fn links(links: &[(&str, bool)]) -> Vec<(String, bool)> {
+    links
+        .iter()
+        .map(|(name, paused)| (name.to_string(), *paused))
+        .collect()
+}
+
+#[test]
+fn pauses_and_resumes_links_with_their_interface() {
+    let eth0 = interface("eth0", 1, UP);
+    let wlan0 = interface("wlan0", 2, UP);
+
+    // nothing changes while everything is up
+    let current = links(&[("eth0", false), ("wlan0", false)]);
+    assert!(link_changes(&current, &[eth0.clone(), wlan0.clone()]).is_empty());
+
+    // gone, or listed but without a link, both pause. the other link carries on,
+    // so there's no failing over
+    assert_eq!(
+        link_changes(&current, std::slice::from_ref(&eth0)),
+        vec![LinkChange::Pause(1)]
+    );
+    assert_eq!(
+        link_changes(&current, &[eth0.clone(), interface("wlan0", 2, 0x1)]),
+        vec![LinkChange::Pause(1)]
+    );
+
+    let paused = links(&[("eth0", false), ("wlan0", true)]);
+    assert_eq!(
+        link_changes(&paused, &[eth0, wlan0.clone()]),
+        vec![LinkChange::Resume(1, wlan0)]
+    );
+}
+
+#[test]
+fn fails_over_once_every_link_is_down() {
+    let current = links(&[("eth0", false), ("wlan0", true)]);
+    let usb0 = interface("usb0", 3, UP);
+    let down = interface("eth1", 4, 0);
+
+    // a spare that is down is no use, the next one up is taken
+    assert_eq!(
+        link_changes(&current, &[down.clone(), usb0.clone()]),
+        vec![LinkChange::Pause(0), LinkChange::FailOver(usb0.clone())]
+    );
+
+    // nowhere to go, so the links wait to come back
+    assert_eq!(link_changes(&current, &[down]), vec![LinkChange::Pause(0)]);
+
+    // with everything already paused a new interface is picked up straight away
+    let paused = links(&[("eth0", true), ("wlan0", true)]);
+    assert_eq!(
+        link_changes(&paused, std::slice::from_ref(&usb0)),
+        vec![LinkChange::FailOver(usb0)]
+    );
+}
@@ -0,0 +1,81 @@
+use floodfile::network::frame::CHUNK_SIZE;
+use floodfile::network::loopback::Loopback;
+use floodfile::network::pacing::Pacer;
+use floodfile::network::payload::Payload;
+use pnet::util::MacAddr;
+use std::time::{Duration, Instant};
+
+#[test]
+fn increases_additively_and_halves_on_loss() {
+    let mut pacer = Pacer::default();
+    let start = pacer.rate();
+    let now = Instant::now();
+
+    pacer.report(100, 100, now);
+    assert_eq!(pacer.rate(), start + 100);
+
+    pacer.report(50, 100, now);
+    assert_eq!(pacer.rate(), (start + 100) / 2);
+
+    // a second receiver reporting the same burst doesn't halve it again
+    pacer.report(50, 100, now + Duration::from_millis(10));
+    assert_eq!(pacer.rate(), (start + 100) / 2);
+
+    pacer.report(50, 100, now + Duration::from_secs(2));
+    assert_eq!(pacer.rate(), (start + 100) / 4);
+}
+
+#[test]
+fn spaces_frames_by_the_rate() {
+    let mut pacer = Pacer::default();
+    let now = Instant::now();
+    assert!(pacer.ready(now));
+
+    pacer.sent(now);
+    let interval = Duration::from_secs_f64(1.0 / f64::from(pacer.rate()));
+    assert!(!pacer.ready(now));
+    assert!(pacer.ready(now + interval));
+}
+
+#[test]
+fn late_joiners_do_not_slow_the_sender() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+
+    // noise, so compression can't make it any shorter than 200 chunks
+    let mut seed = 1u32;
+    let data: Vec<u8> = (0..200 * CHUNK_SIZE)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect();
+    let key = [4; 8];
+    sender
+        .queue_with_key(Payload::Push(String::from("noise.bin"), data), key)
+        .unwrap();
+
+    let mut send = |frames: usize| {
+        let mut sent = 0;
+        while sent < frames {
+            sent += sender.pump(1).unwrap();
+            std::thread::sleep(Duration::from_micros(200));
+        }
+    };
+
+    // the receiver turns up well into the transfer and misses nothing after that
+    send(20);
+    let mut receiver = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+    send(50);
+    for _ in 0..50 {
+        receiver.poll().unwrap();
+    }
+
+    let reports = receiver.loss_reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!((reports[0].received, reports[0].expected), (50, 50));
+
+    let rate = sender.send_rate();
+    sender.report_loss(key, reports[0].received, reports[0].expected);
+    assert!(sender.send_rate() > rate);
+}
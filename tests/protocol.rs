@@ -0,0 +1,36 @@
+use floodfile::network::payload::Payload;
+use floodfile::protocol::{Event, Protocol};
+use pnet::util::MacAddr;
+
+#[test]
+fn reassembles_out_of_order_frames() {
+    let sender_mac = MacAddr::new(2, 0, 0, 0, 0, 1);
+    let mut sender = Protocol::with_nonce(sender_mac, [1; 4]);
+    let mut receiver = Protocol::with_nonce(MacAddr::new(2, 0, 0, 0, 0, 2), [2; 4]);
+
+    let data: Vec<u8> = (0..8192u32).map(|i| (i * 7919 % 251) as u8).collect();
+    let mut frames = sender
+        .encode(&Payload::File([3; 16], data.clone()))
+        .unwrap();
+    assert!(frames.len() > 1);
+
+    // our own frames never surface as events
+    for frame in frames.iter() {
+        assert!(sender.decode(frame).unwrap().is_none());
+    }
+
+    frames.reverse();
+    let (last, rest) = frames.split_last().unwrap();
+    for frame in rest {
+        assert!(receiver.decode(frame).unwrap().is_none());
+    }
+
+    match receiver.decode(last).unwrap() {
+        Some(Event::Received(src, Payload::File(hash, file))) => {
+            assert_eq!(src, sender_mac);
+            assert_eq!(hash, [3; 16]);
+            assert_eq!(file, data);
+        }
+        other => panic!("expected a file payload, got {0:?}", other),
+    }
+}
@@ -0,0 +1,63 @@
+use floodfile::files;
+use floodfile::session::push::{Decision, PushPolicy, Pushes};
+use pnet::util::MacAddr;
+use std::fs;
+
+#[test]
+fn trusted_peers_skip_the_prompt() {
+    let trusted = MacAddr::new(2, 0, 0, 0, 0, 1);
+    let stranger = MacAddr::new(2, 0, 0, 0, 0, 2);
+
+    let mut pushes = Pushes::default();
+    pushes.trusted.insert(trusted);
+    assert!(matches!(pushes.decide(trusted), Decision::Prompt));
+
+    pushes.policy = PushPolicy::AcceptTrusted;
+    assert!(matches!(pushes.decide(trusted), Decision::Accept));
+    assert!(matches!(pushes.decide(stranger), Decision::Prompt));
+
+    pushes.policy = PushPolicy::Ignore;
+    assert!(matches!(pushes.decide(trusted), Decision::Ignore));
+}
+
+#[test]
+fn offered_pushes_are_taken_once() {
+    let mut pushes = Pushes::default();
+    let id = pushes.offer(MacAddr::zero(), String::from("notes.txt"), vec![1, 2, 3]);
+
+    let push = pushes.take(id).unwrap();
+    assert_eq!(
+        (push.name.as_str(), push.data),
+        ("notes.txt", vec![1, 2, 3])
+    );
+    assert!(pushes.take(id).is_none());
+}
+
+#[test]
+fn pending_pushes_are_capped_in_bytes() {
+    let mut pushes = Pushes::default();
+    let big = vec![0; 15 * 1024 * 1024];
+    let first = pushes.offer(MacAddr::zero(), String::from("a.bin"), big.clone());
+    pushes.offer(MacAddr::zero(), String::from("b.bin"), big.clone());
+    pushes.offer(MacAddr::zero(), String::from("c.bin"), big);
+
+    // the oldest made way
+    assert!(pushes.pending_bytes() <= 32 * 1024 * 1024);
+    assert!(pushes.take(first).is_none());
+}
+
+#[test]
+fn pushed_files_never_replace_existing_ones() {
+    let dir = std::env::temp_dir().join(format!("floodfile-push-{0}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("notes.txt"), b"mine").unwrap();
+
+    let first = files::save_new(&dir, "../notes.txt", b"pushed").unwrap();
+    let second = files::save_new(&dir, "notes.txt", b"again").unwrap();
+    let kept = fs::read(dir.join("notes.txt")).unwrap();
+    fs::remove_dir_all(&dir).ok();
+
+    assert_eq!(kept, b"mine");
+    assert_eq!(first, dir.join("notes (1).txt"));
+    assert_eq!(second, dir.join("notes (2).txt"));
+}
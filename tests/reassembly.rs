@@ -0,0 +1,102 @@
+use floodfile::errors::FloodFileError;
+use floodfile::network::frame::Frame;
+use floodfile::network::reassembly::{Progress, Reassembler, REASSEMBLY_TTL};
+use pnet::util::MacAddr;
+use std::time::Instant;
+
+fn chunk(source: MacAddr, key: u8, offset: u16, total: u16) -> Frame {
+    Frame {
+        source,
+        relayed_by: None,
+        vlan: None,
+        opcode: 1,
+        hops: 0,
+        offset,
+        total,
+        key: [key; 8],
+        nonce: [0; 4],
+        chunk: vec![key; 4],
+    }
+}
+
+#[test]
+fn rejects_offsets_past_the_total() {
+    let mut reassembler = Reassembler::default();
+    let source = MacAddr::new(2, 0, 0, 0, 0, 1);
+
+    let result = reassembler.push(chunk(source, 1, 2, 2));
+    assert!(matches!(result, Err(FloodFileError::ReassemblyMismatch)));
+    assert_eq!(reassembler.pending(), 0);
+
+    assert!(matches!(
+        reassembler.push(chunk(source, 1, 1, 2)),
+        Ok(Progress::Partial {
+            received: 1,
+            total: 2
+        })
+    ));
+}
+
+#[test]
+fn forgets_packets_that_stopped_arriving() {
+    let mut reassembler = Reassembler::default();
+    reassembler
+        .push(chunk(MacAddr::new(2, 0, 0, 0, 0, 1), 1, 0, 2))
+        .unwrap();
+
+    assert_eq!(reassembler.prune(Instant::now()), 0);
+    assert_eq!(reassembler.prune(Instant::now() + REASSEMBLY_TTL), 1);
+    assert_eq!(reassembler.pending(), 0);
+}
+
+#[test]
+fn caps_unfinished_packets_per_sender() {
+    let mut reassembler = Reassembler::default();
+    let flooder = MacAddr::new(2, 0, 0, 0, 0, 1);
+    for key in 0..100 {
+        reassembler.push(chunk(flooder, key, 0, 2)).unwrap();
+    }
+    assert!(reassembler.pending() < 100);
+
+    // others still get through, and the newest of the flood is kept
+    let before = reassembler.pending();
+    reassembler
+        .push(chunk(MacAddr::new(2, 0, 0, 0, 0, 2), 1, 0, 2))
+        .unwrap();
+    assert_eq!(reassembler.pending(), before + 1);
+    assert!(matches!(
+        reassembler.push(chunk(flooder, 99, 1, 2)),
+        Ok(Progress::Complete { .. })
+    ));
+}
+
+#[test]
+fn counts_loss_from_where_the_receiver_joined() {
+    let mut reassembler = Reassembler::default();
+    let source = MacAddr::new(2, 0, 0, 0, 0, 1);
+    let report = |reassembler: &mut Reassembler| {
+        let reports = reassembler.loss_reports();
+        assert_eq!(reports.len(), 1);
+        (reports[0].received, reports[0].expected)
+    };
+
+    // joined a carousel at offset 40, nothing before that was missed
+    for offset in 40..50 {
+        reassembler.push(chunk(source, 1, offset, 50)).ok();
+    }
+    assert_eq!(report(&mut reassembler), (10, 10));
+
+    // wrapping round to the start carries on from there, a gap inside is lost
+    for offset in [0, 1, 3] {
+        reassembler.push(chunk(source, 1, offset, 50)).ok();
+    }
+    assert_eq!(report(&mut reassembler), (3, 4));
+
+    // a chunk reordered just before the first one seen isn't a wrap either
+    for offset in [21, 20, 22] {
+        reassembler.push(chunk(source, 2, offset, 50)).ok();
+    }
+    let reports = reassembler.loss_reports();
+    let late = reports.iter().find(|r| r.key == [2; 8]).unwrap();
+    assert_eq!((late.received, late.expected), (3, 3));
+}
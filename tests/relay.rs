@@ -0,0 +1,107 @@
+use floodfile::network::frame::Frame;
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::Payload;
+use floodfile::network::relay::{Relay, RelayConfig};
+use floodfile::network::Channel;
+use floodfile::session::Session;
+use pnet::util::MacAddr;
+use std::time::{Duration, Instant};
+
+fn frame(offset: u16, hops: u8) -> Frame {
+    Frame {
+        source: MacAddr::new(2, 0, 0, 0, 0, 1),
+        relayed_by: None,
+        vlan: None,
+        opcode: 1,
+        hops,
+        offset,
+        total: 100,
+        key: [1; 8],
+        nonce: [2; 4],
+        chunk: vec![0; 8],
+    }
+}
+
+#[test]
+fn forwards_each_chunk_once_within_limits() {
+    let mut relay = Relay::new(RelayConfig {
+        max_hops: 2,
+        rate: 2,
+    });
+    let now = Instant::now();
+
+    // forwarded with the hop count bumped, then suppressed when it loops back
+    let forwarded = relay.forward(&frame(0, 0), now).unwrap();
+    assert_eq!(forwarded.hops, 1);
+    assert!(relay.forward(&forwarded, now).is_none());
+
+    // too many hops already
+    assert!(relay.forward(&frame(1, 2), now).is_none());
+
+    // the second token is spent, the third chunk in the same instant is dropped
+    assert!(relay.forward(&frame(2, 0), now).is_some());
+    assert!(relay.forward(&frame(3, 0), now).is_none());
+    assert!(relay
+        .forward(&frame(3, 0), now + Duration::from_secs(1))
+        .is_some());
+}
+
+// every frame seen on the channel until it stays quiet for a moment
+fn collect(channel: &Channel) -> Vec<Frame> {
+    let frames = channel.frames();
+    let mut seen = vec![];
+    while let Ok(data) = frames.recv_timeout(Duration::from_millis(500)) {
+        if let Some((frame, _)) = Frame::decode(&data) {
+            seen.push(frame);
+        }
+    }
+    seen
+}
+
+#[test]
+fn relays_between_two_segments() {
+    let (left, right) = (Loopback::named("left"), Loopback::named("right"));
+    let host = MacAddr::new(2, 0, 0, 0, 0, 1);
+    let relay_mac = MacAddr::new(2, 0, 0, 0, 0, 2);
+
+    let relay =
+        Session::with_channels(vec![left.channel(relay_mac), right.channel(relay_mac)]).unwrap();
+    relay
+        .set_relay(Some(RelayConfig {
+            max_hops: 2,
+            rate: 100,
+        }))
+        .unwrap();
+
+    // queries are answered in order, so relaying is on once this returns
+    assert_eq!(
+        relay.active_interfaces().unwrap(),
+        vec![String::from("left"), String::from("right")]
+    );
+
+    let mut sender = left.channel(host);
+    let neighbour = left.channel(MacAddr::new(2, 0, 0, 0, 0, 3));
+    let observer = right.channel(MacAddr::new(2, 0, 0, 0, 0, 4));
+
+    sender
+        .send(Payload::Advertise(String::from("/tmp/notes.txt")))
+        .unwrap();
+
+    // the far segment sees the original sender, one hop further on. the relay's
+    // own traffic (its discovery on start) is left out
+    let relayed: Vec<Frame> = collect(&observer)
+        .into_iter()
+        .filter(|f| f.source != relay_mac)
+        .collect();
+    assert_eq!(relayed.len(), 1);
+    assert_eq!(relayed[0].source, host);
+    assert_eq!(relayed[0].relayed_by, Some(relay_mac));
+    assert_eq!(relayed[0].hops, 1);
+
+    // and nothing is sent back onto the segment it came from
+    let near = collect(&neighbour);
+    assert_eq!(near.iter().filter(|f| f.source == host).count(), 1);
+    assert!(near.iter().all(|f| f.relayed_by.is_none() && f.hops == 0));
+
+    relay.shutdown().ok();
+}
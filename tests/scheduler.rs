@@ -0,0 +1,97 @@
+use floodfile::network::scheduler::{Priority, Scheduler};
+
+fn frames(tag: u8, count: usize) -> Vec<Vec<u8>> {
+    (0..count).map(|i| vec![tag, i as u8]).collect()
+}
+
+#[test]
+fn interleaves_transfers_and_puts_control_first() {
+    let mut scheduler = Scheduler::default();
+    scheduler.push([1; 8], Priority::Bulk, frames(1, 2));
+    scheduler.push([2; 8], Priority::Bulk, frames(2, 2));
+    scheduler.push([3; 8], Priority::Control, frames(3, 1));
+
+    let order: Vec<_> = std::iter::from_fn(|| scheduler.pop())
+        .map(|outgoing| outgoing.frame)
+        .collect();
+
+    assert_eq!(
+        order,
+        vec![vec![3, 0], vec![1, 0], vec![2, 0], vec![1, 1], vec![2, 1]]
+    );
+    assert!(scheduler.is_empty());
+}
+
+#[test]
+fn cancels_a_transfer() {
+    let mut scheduler = Scheduler::default();
+    scheduler.push([1; 8], Priority::Bulk, frames(1, 3));
+    scheduler.push([2; 8], Priority::Bulk, frames(2, 1));
+    scheduler.cancel(&[1; 8]);
+
+    let next = scheduler.pop().unwrap();
+    assert_eq!((next.key, next.frame), ([2; 8], vec![2, 0]));
+    assert!(scheduler.pop().is_none());
+}
+
+#[test]
+fn retried_frames_keep_their_offset_and_count_errors() {
+    let mut scheduler = Scheduler::default();
+    scheduler.push([1; 8], Priority::Bulk, frames(1, 3));
+
+    scheduler.pop().unwrap();
+    let failed = scheduler.pop().unwrap();
+    assert_eq!(failed.offset, 1);
+    scheduler.retry(failed);
+
+    let again = scheduler.pop().unwrap();
+    assert_eq!((again.offset, again.retries, again.errors), (1, 1, 1));
+    assert_eq!(again.frame, vec![1, 1]);
+
+    // a successful send clears the retries but not the transfer's error count
+    let last = scheduler.pop().unwrap();
+    assert_eq!((last.offset, last.retries, last.errors), (2, 0, 1));
+}
+
+#[test]
+fn interrupted_transfers_wait_until_requeued() {
+    let mut scheduler = Scheduler::default();
+    scheduler.push([1; 8], Priority::Bulk, frames(1, 3));
+    scheduler.push([2; 8], Priority::Bulk, frames(2, 2));
+
+    scheduler.pop().unwrap();
+    scheduler.pop().unwrap();
+    let failed = scheduler.pop().unwrap();
+    assert_eq!((failed.key, failed.offset), ([1; 8], 1));
+    scheduler.interrupt(failed);
+    assert_eq!(scheduler.interrupted(), vec![[1; 8]]);
+    assert!(!scheduler.contains(&[1; 8]));
+
+    let other = scheduler.pop().unwrap();
+    assert_eq!(other.key, [2; 8]);
+    assert!(scheduler.pop().is_none());
+
+    // picks up from the frame that failed, with the error counted
+    assert!(scheduler.requeue(&[1; 8]));
+    assert!(!scheduler.requeue(&[1; 8]));
+    let again = scheduler.pop().unwrap();
+    assert_eq!((again.offset, again.retries, again.errors), (1, 0, 1));
+    assert_eq!(again.frame, vec![1, 1]);
+}
+
+#[test]
+fn discards_everything_below_a_priority() {
+    let mut scheduler = Scheduler::default();
+    scheduler.push([1; 8], Priority::Bulk, frames(1, 3));
+    scheduler.push([2; 8], Priority::Background, frames(2, 1));
+    scheduler.push([3; 8], Priority::Bulk, frames(3, 2));
+    let failed = scheduler.pop().unwrap();
+    scheduler.interrupt(failed);
+    scheduler.push([4; 8], Priority::Control, frames(4, 1));
+
+    // interrupted transfers go too, they'd only be requeued as bulk
+    scheduler.discard_below(Priority::Control);
+    assert!(scheduler.interrupted().is_empty());
+    assert_eq!(scheduler.pop().unwrap().key, [4; 8]);
+    assert!(scheduler.pop().is_none());
+}
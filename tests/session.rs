@@ -0,0 +1,457 @@
+use crossbeam::channel::RecvTimeoutError;
+use floodfile::errors::FloodFileError;
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::{CatalogEntry, Payload};
+use floodfile::session::config::Config;
+use floodfile::session::timers::Timer;
+use floodfile::session::{Event, Session};
+use pnet::util::MacAddr;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("floodfile-{0}-{1}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// the first event matching, skipping the rest
+fn wait_for<T>(session: &Session, mut matches: impl FnMut(Event) -> Option<T>) -> T {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match session.events().recv_deadline(deadline) {
+            Ok(event) => {
+                if let Some(found) = matches(event) {
+                    return found;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => panic!("timed out waiting for an event"),
+            Err(RecvTimeoutError::Disconnected) => panic!("session closed"),
+        }
+    }
+}
+
+#[test]
+fn downloads_a_share_between_sessions() {
+    let bus = Loopback::new();
+    let sharer = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+    let downloader = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2))).unwrap();
+
+    let shared = scratch_dir("session-shared");
+    let saved = scratch_dir("session-saved");
+    let path = shared.join("notes.txt");
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * 7919 % 251) as u8).collect();
+    fs::write(&path, &data).unwrap();
+
+    downloader.set_download_dir(&saved).unwrap();
+    sharer.share(&path).unwrap();
+
+    let share = wait_for(&downloader, |event| match event {
+        Event::ShareDiscovered(share) => Some(share),
+        _ => None,
+    });
+    assert!(share.name.ends_with("notes.txt"));
+
+    let download = downloader.download(share.id).unwrap();
+    let saved_to = wait_for(&downloader, |event| match event {
+        Event::DownloadComplete { id, path } if id == download.id() => Some(path),
+        Event::DownloadFailed { error, .. } => panic!("download failed: {0}", error),
+        _ => None,
+    });
+
+    let received = fs::read(&saved_to).unwrap();
+    sharer.shutdown().ok();
+    downloader.shutdown().ok();
+    fs::remove_dir_all(&shared).ok();
+    fs::remove_dir_all(&saved).ok();
+
+    assert!(received == data);
+}
+
+#[test]
+fn serves_a_share_on_every_link() {
+    let (left, right) = (Loopback::named("left"), Loopback::named("right"));
+    let sharer_mac = MacAddr::new(2, 0, 0, 0, 0, 1);
+    let sharer =
+        Session::with_channels(vec![left.channel(sharer_mac), right.channel(sharer_mac)]).unwrap();
+    assert_eq!(
+        sharer.active_interfaces().unwrap(),
+        vec![String::from("left"), String::from("right")]
+    );
+
+    let shared = scratch_dir("links-shared");
+    let path = shared.join("notes.txt");
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * 7919 % 251) as u8).collect();
+    fs::write(&path, &data).unwrap();
+
+    let downloaders: Vec<_> = [(&left, 2u8), (&right, 3u8)]
+        .into_iter()
+        .map(|(bus, id)| {
+            let session =
+                Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, id))).unwrap();
+            let saved = scratch_dir(&format!("links-saved-{0}", id));
+            session.set_download_dir(&saved).unwrap();
+            (session, saved)
+        })
+        .collect();
+
+    sharer.share(&path).unwrap();
+
+    // each segment hears about the share and fetches it from its own side
+    for (downloader, saved) in downloaders.iter() {
+        let share = wait_for(downloader, |event| match event {
+            Event::ShareDiscovered(share) => Some(share),
+            _ => None,
+        });
+        assert_eq!(share.peer, sharer_mac);
+
+        let download = downloader.download(share.id).unwrap();
+        let saved_to = wait_for(downloader, |event| match event {
+            Event::DownloadComplete { id, path } if id == download.id() => Some(path),
+            Event::DownloadFailed { error, .. } => panic!("download failed: {0}", error),
+            _ => None,
+        });
+
+        assert!(saved_to.starts_with(saved));
+        assert!(fs::read(&saved_to).unwrap() == data);
+    }
+
+    sharer.shutdown().ok();
+    for (downloader, saved) in downloaders {
+        downloader.shutdown().ok();
+        fs::remove_dir_all(&saved).ok();
+    }
+    fs::remove_dir_all(&shared).ok();
+}
+
+#[test]
+fn unsharing_stops_the_share_everywhere() {
+    let bus = Loopback::new();
+    let sharer = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+    let peer = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2))).unwrap();
+
+    let shared = scratch_dir("unshare");
+    let path = shared.join("notes.txt");
+    fs::write(&path, b"short lived").unwrap();
+
+    sharer.share(&path).unwrap();
+    let share = wait_for(&peer, |event| match event {
+        Event::ShareDiscovered(share) => Some(share),
+        _ => None,
+    });
+
+    // still found by its path once the file itself is gone
+    fs::remove_dir_all(&shared).ok();
+    sharer.unshare(&path).unwrap();
+
+    let stopped = wait_for(&sharer, |event| match event {
+        Event::ShareStopped(stopped) => Some(stopped),
+        _ => None,
+    });
+    assert_eq!(stopped, path.to_str().unwrap());
+    assert!(sharer.local_shares().unwrap().is_empty());
+
+    let withdrawn = wait_for(&peer, |event| match event {
+        Event::ShareWithdrawn(id) => Some(id),
+        _ => None,
+    });
+    assert_eq!(withdrawn, share.id);
+
+    sharer.shutdown().ok();
+    peer.shutdown().ok();
+}
+
+#[test]
+fn retries_a_request_that_failed_to_send() {
+    let bus = Loopback::new();
+    let sharer = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+    let downloader = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2))).unwrap();
+
+    let shared = scratch_dir("retry-shared");
+    let saved = scratch_dir("retry-saved");
+    let path = shared.join("notes.txt");
+    fs::write(&path, b"sent on the second try").unwrap();
+
+    downloader.set_download_dir(&saved).unwrap();
+    sharer.share(&path).unwrap();
+    let share = wait_for(&downloader, |event| match event {
+        Event::ShareDiscovered(share) => Some(share),
+        _ => None,
+    });
+
+    // the link stays up as far as the session can tell, only the send fails
+    bus.set_down(true);
+    let download = downloader.download(share.id).unwrap();
+    wait_for(&downloader, |event| match event {
+        Event::Error(_) => Some(()),
+        _ => None,
+    });
+    bus.set_down(false);
+
+    let saved_to = wait_for(&downloader, |event| match event {
+        Event::DownloadComplete { id, path } if id == download.id() => Some(path),
+        Event::DownloadFailed { error, .. } => panic!("download failed: {0}", error),
+        _ => None,
+    });
+    assert_eq!(fs::read(&saved_to).unwrap(), b"sent on the second try");
+
+    sharer.shutdown().ok();
+    downloader.shutdown().ok();
+    fs::remove_dir_all(&shared).ok();
+    fs::remove_dir_all(&saved).ok();
+}
+
+#[test]
+fn lists_shares_first_seen_in_a_catalog() {
+    let bus = Loopback::new();
+    let browser = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+    let peer_mac = MacAddr::new(2, 0, 0, 0, 0, 2);
+    let mut peer = bus.channel(peer_mac);
+
+    // catalogs are only asked of peers that have been heard from
+    peer.send(Payload::Discover).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !browser.peers().unwrap().contains(&peer_mac) {
+        assert!(Instant::now() < deadline, "peer never heard");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    // answered in order, so the request is out before the catalog comes back
+    browser.request_catalog(peer_mac).unwrap();
+    browser.active_interfaces().unwrap();
+
+    let entry = CatalogEntry {
+        name: String::from("/srv/notes.txt"),
+        size: 11,
+    };
+    peer.send(Payload::Catalog(0, 1, vec![entry])).unwrap();
+
+    let share = wait_for(&browser, |event| match event {
+        Event::ShareDiscovered(share) => Some(share),
+        _ => None,
+    });
+    assert_eq!(share.name, "/srv/notes.txt");
+    assert_eq!(share.peer, peer_mac);
+
+    // the advertisement that follows doesn't list it twice
+    peer.send(Payload::Advertise(String::from("/srv/notes.txt")))
+        .unwrap();
+    assert!(browser.remote_shares().unwrap().len() == 1);
+    let listed = browser
+        .events()
+        .try_iter()
+        .filter(|e| matches!(e, Event::ShareDiscovered(_)))
+        .count();
+    assert_eq!(listed, 0);
+
+    browser.shutdown().ok();
+}
+
+#[test]
+fn cancelled_requests_are_not_retried() {
+    let bus = Loopback::new();
+    let downloader = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    peer.send(Payload::Advertise(String::from("/srv/notes.txt")))
+        .unwrap();
+    let share = wait_for(&downloader, |event| match event {
+        Event::ShareDiscovered(share) => Some(share),
+        _ => None,
+    });
+
+    // the request fails to send and waits for the retry timer
+    bus.set_down(true);
+    let download = downloader.download(share.id).unwrap();
+    wait_for(&downloader, |event| match event {
+        Event::Error(_) => Some(()),
+        _ => None,
+    });
+    download.cancel().unwrap();
+    downloader.active_interfaces().unwrap();
+    bus.set_down(false);
+
+    // well past the retry, nothing asks the peer for the file
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < deadline {
+        if let Ok(Some((_, payload))) = peer.recv() {
+            assert!(!matches!(payload, Payload::DownloadRequest(_)));
+        }
+    }
+
+    downloader.shutdown().ok();
+}
+
+#[test]
+fn keeps_running_when_nobody_reads_events() {
+    let bus = Loopback::new();
+    let session = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+    let mut peer = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    // each advertisement is an event, more than the backlog holds
+    let capacity = session.events().capacity().unwrap();
+    for i in 0..capacity + 100 {
+        peer.send(Payload::Advertise(format!("/srv/{0}.txt", i)))
+            .unwrap();
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while session.remote_shares().unwrap().len() < capacity + 100 {
+        assert!(Instant::now() < deadline, "stopped handling frames");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    // the oldest are kept, the rest dropped
+    assert_eq!(session.events().len(), capacity);
+    let first = wait_for(&session, |event| match event {
+        Event::ShareDiscovered(share) => Some(share),
+        _ => None,
+    });
+    assert_eq!(first.name, "/srv/0.txt");
+
+    session.shutdown().ok();
+}
+
+#[test]
+fn reports_progress_once_per_percent() {
+    let bus = Loopback::new();
+    let sharer = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+    let downloader = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2))).unwrap();
+
+    let shared = scratch_dir("progress-shared");
+    let saved = scratch_dir("progress-saved");
+    let path = shared.join("noise.bin");
+
+    // doesn't compress, so it takes a few hundred chunks
+    let mut state = 1u32;
+    let data: Vec<u8> = (0..100_000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect();
+    fs::write(&path, &data).unwrap();
+
+    downloader.set_download_dir(&saved).unwrap();
+    sharer.share(&path).unwrap();
+    let share = wait_for(&downloader, |event| match event {
+        Event::ShareDiscovered(share) => Some(share),
+        _ => None,
+    });
+
+    let download = downloader.download(share.id).unwrap();
+    let mut progress = vec![];
+    wait_for(&downloader, |event| match event {
+        Event::DownloadProgress {
+            received, total, ..
+        } => {
+            progress.push(received * 100 / total);
+            None
+        }
+        Event::DownloadComplete { id, .. } if id == download.id() => Some(()),
+        Event::DownloadFailed { error, .. } => panic!("download failed: {0}", error),
+        _ => None,
+    });
+
+    sharer.shutdown().ok();
+    downloader.shutdown().ok();
+    fs::remove_dir_all(&shared).ok();
+    fs::remove_dir_all(&saved).ok();
+
+    // a few hundred chunks, but never two events for the same percent
+    assert!(progress.len() > 1);
+    assert!(progress.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn clamps_the_advertise_interval() {
+    let bus = Loopback::new();
+    let session = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+
+    // too far out to schedule, the worker carries on with a day instead
+    session.set_advertise_interval(Duration::MAX).unwrap();
+    assert_eq!(
+        session.active_interfaces().unwrap(),
+        vec![String::from("loopback")]
+    );
+
+    session.shutdown().ok();
+}
+
+#[test]
+fn rejects_a_carousel_that_never_ends() {
+    let bus = Loopback::new();
+    let session = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+
+    let shared = scratch_dir("carousel-forever");
+    let path = shared.join("slides.pdf");
+    fs::write(&path, b"slides").unwrap();
+
+    session.carousel(&path, Duration::MAX).unwrap();
+    wait_for(&session, |event| match event {
+        Event::Error(error) => Some(error),
+        Event::Sharing(_) => panic!("started a carousel with no end"),
+        _ => None,
+    });
+    assert!(session.local_shares().unwrap().is_empty());
+
+    session.shutdown().ok();
+    fs::remove_dir_all(&shared).ok();
+}
+
+#[test]
+fn transfer_timers_run_only_while_receiving() {
+    let bus = Loopback::new();
+    let mut cfg = Config::with_channels(vec![bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))]);
+    assert!(cfg.timers.deadline(Timer::LossReport).is_none());
+    assert!(cfg.timers.deadline(Timer::Prune).is_none());
+
+    // the first chunk of a push that takes many
+    let mut state = 1u32;
+    let data: Vec<u8> = (0..10_000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+    sender
+        .queue(Payload::Push(String::from("noise.bin"), data))
+        .unwrap();
+    sender.pump(1).unwrap();
+
+    let frame = cfg.links[0]
+        .channel
+        .frames()
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap();
+    cfg.links[0].channel.process(&frame).unwrap();
+    cfg.schedule_transfer_timers();
+    assert!(cfg.timers.deadline(Timer::LossReport).is_some());
+    assert!(cfg.timers.deadline(Timer::Prune).is_some());
+
+    // once it's pruned there's nothing left to report on
+    cfg.links[0]
+        .channel
+        .prune(Instant::now() + Duration::from_secs(3600));
+    assert!(!cfg.receiving());
+}
+
+#[test]
+fn refuses_snippets_that_cannot_be_sent() {
+    let bus = Loopback::new();
+    let session = Session::with_channel(bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1))).unwrap();
+
+    assert!(matches!(
+        session.send_snippet(""),
+        Err(FloodFileError::EmptySnippet)
+    ));
+    assert!(matches!(
+        session.send_snippet(&"x".repeat(Payload::MAX_SNIPPET_LEN + 1)),
+        Err(FloodFileError::SnippetTooLarge)
+    ));
+    assert!(session.send_snippet("ls -la").is_ok());
+
+    session.shutdown().ok();
+}
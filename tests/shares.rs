@@ -0,0 +1,47 @@
+use floodfile::session::{LocalShare, ShareOptions};
+use std::time::{Duration, Instant, SystemTime};
+
+#[test]
+fn scheduled_shares_wait_for_their_start() {
+    let now = Instant::now();
+    let options = ShareOptions {
+        start_at: Some(SystemTime::now() + Duration::from_secs(3600)),
+        stop_after: Some(Duration::from_secs(1800)),
+        ..Default::default()
+    };
+
+    let share = LocalShare::scheduled(String::from("/tmp/build.tar"), &options, now).unwrap();
+    assert!(!share.live(now));
+    assert!(share.live(now + Duration::from_secs(3601)));
+
+    // the half hour counts from the start, not from when it was scheduled
+    assert!(!share.exhausted(now + Duration::from_secs(3601)));
+    assert!(share.exhausted(now + Duration::from_secs(5401)));
+}
+
+#[test]
+fn shares_run_out_of_downloads() {
+    let now = Instant::now();
+    let options = ShareOptions {
+        max_downloads: Some(2),
+        ..Default::default()
+    };
+
+    let mut share = LocalShare::scheduled(String::from("/tmp/notes.txt"), &options, now).unwrap();
+    assert!(share.live(now));
+
+    share.downloads = 1;
+    assert!(!share.exhausted(now));
+    share.downloads = 2;
+    assert!(share.exhausted(now));
+}
+
+#[test]
+fn schedules_too_far_out_are_refused() {
+    let now = Instant::now();
+    let forever = ShareOptions {
+        stop_after: Some(Duration::MAX),
+        ..Default::default()
+    };
+    assert!(LocalShare::scheduled(String::from("/tmp/notes.txt"), &forever, now).is_none());
+}
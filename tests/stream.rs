@@ -0,0 +1,131 @@
+use crossbeam::channel::{unbounded, Receiver};
+use floodfile::errors::FloodFileError;
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::Payload;
+use floodfile::network::stream::{self, SEGMENT_SIZE};
+use pnet::util::MacAddr;
+use std::io::{self, Cursor, Read};
+use std::thread;
+use std::time::Duration;
+
+// input that arrives as the test hands it over, ending once the sender is dropped
+struct Feed(Receiver<Vec<u8>>, Vec<u8>);
+
+impl Read for Feed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.1.is_empty() {
+            match self.0.recv() {
+                Ok(data) => self.1 = data,
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.1.len());
+        buf[..len].copy_from_slice(&self.1[..len]);
+        self.1.drain(..len);
+        Ok(len)
+    }
+}
+
+#[test]
+fn streams_input_of_unknown_length_in_order() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut receiver = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    // a few segments, the last one partial
+    let input: Vec<u8> = (0..SEGMENT_SIZE * 3 + 1000)
+        .map(|i| (i * 7 % 251) as u8)
+        .collect();
+    let id = [4; 16];
+
+    let sending = {
+        let input = input.clone();
+        thread::spawn(move || {
+            stream::send(&mut sender, Cursor::new(input), id, Duration::from_secs(10))
+        })
+    };
+
+    let mut output = vec![];
+    let written = stream::receive(&mut receiver, &mut output, id, Duration::from_secs(10)).unwrap();
+
+    assert_eq!(written, input.len() as u64);
+    assert!(output == input);
+    assert_eq!(sending.join().unwrap().unwrap(), 4);
+}
+
+#[test]
+fn receivers_give_up_without_a_sender() {
+    let bus = Loopback::new();
+    let mut receiver = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+
+    let mut output = vec![];
+    let result = stream::receive(
+        &mut receiver,
+        &mut output,
+        [4; 16],
+        Duration::from_millis(300),
+    );
+
+    assert!(matches!(result, Err(FloodFileError::StreamTimedOut)));
+    assert!(output.is_empty());
+}
+
+#[test]
+fn late_joiners_are_refused_without_holding_others_back() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut early = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+    let mut late = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 3));
+    let id = [5; 16];
+
+    let (feed, input) = unbounded();
+    let sending = thread::spawn(move || {
+        stream::send(
+            &mut sender,
+            Feed(input, vec![]),
+            id,
+            Duration::from_secs(10),
+        )
+    });
+    let receiving = thread::spawn(move || {
+        let mut output = vec![];
+        stream::receive(&mut early, &mut output, id, Duration::from_secs(10)).map(|_| output)
+    });
+
+    // the first receiver moves past the first segments, so they're dropped
+    for i in 0..2u8 {
+        feed.send(vec![i; SEGMENT_SIZE]).unwrap();
+    }
+    thread::sleep(Duration::from_secs(1));
+
+    let mut output = vec![];
+    let result = stream::receive(&mut late, &mut output, id, Duration::from_secs(10));
+    assert!(matches!(result, Err(FloodFileError::StreamRefused)));
+
+    feed.send(vec![2; 1000]).unwrap();
+    drop(feed);
+
+    let output = receiving.join().unwrap().unwrap();
+    assert_eq!(output.len(), SEGMENT_SIZE * 2 + 1000);
+    assert_eq!(sending.join().unwrap().unwrap(), 3);
+}
+
+#[test]
+fn senders_fail_when_a_receiver_never_finishes() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut quitter = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+    let id = [6; 16];
+
+    // asks for the stream once and is never heard from again
+    quitter.send(Payload::StreamRequest(id, 0)).unwrap();
+
+    let result = stream::send(
+        &mut sender,
+        Cursor::new(vec![1; 1000]),
+        id,
+        Duration::from_secs(10),
+    );
+    assert!(matches!(result, Err(FloodFileError::StreamIncomplete)));
+}
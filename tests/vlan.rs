@@ -0,0 +1,94 @@
+use floodfile::network::frame::Frame;
+use floodfile::network::loopback::Loopback;
+use floodfile::network::payload::Payload;
+use floodfile::network::{Channel, Vlan};
+use pnet::util::MacAddr;
+use std::str::FromStr;
+
+fn vlan(id: u16, priority: u8) -> Option<Vlan> {
+    Some(Vlan { id, priority })
+}
+
+// how many payloads are waiting on the channel
+fn received(channel: &mut Channel) -> usize {
+    (0..16)
+        .filter(|_| matches!(channel.recv(), Ok(Some((_, Payload::Advertise(_))))))
+        .count()
+}
+
+#[test]
+fn tagged_payloads_stay_on_their_vlan() {
+    let bus = Loopback::new();
+    let mut sender = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 1));
+    let mut same = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 2));
+    let mut other = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 3));
+    let mut untagged = bus.channel(MacAddr::new(2, 0, 0, 0, 0, 4));
+
+    sender.set_vlan(vlan(10, 5));
+    same.set_vlan(vlan(10, 0));
+    other.set_vlan(vlan(20, 0));
+
+    sender
+        .send(Payload::Advertise(String::from("/tmp/notes.txt")))
+        .unwrap();
+
+    // the priority doesn't matter, and a channel without a vlan takes any
+    assert_eq!(received(&mut same), 1);
+    assert_eq!(received(&mut other), 0);
+    assert_eq!(received(&mut untagged), 1);
+
+    // untagged frames get through to tagged channels
+    sender.set_vlan(None);
+    sender
+        .send(Payload::Advertise(String::from("/tmp/notes.txt")))
+        .unwrap();
+    assert_eq!(received(&mut other), 1);
+}
+
+#[test]
+fn tagged_frames_round_trip() {
+    for tag in [vlan(1, 0), vlan(4094, 7)] {
+        let sent = Frame {
+            source: MacAddr::new(2, 0, 0, 0, 0, 1),
+            relayed_by: None,
+            vlan: tag,
+            opcode: 1,
+            hops: 0,
+            offset: 0,
+            total: 1,
+            key: [1; 8],
+            nonce: [2; 4],
+            chunk: Payload::Advertise(String::from("/tmp/notes.txt")).serialize(),
+        };
+        let data = sent.encode().unwrap();
+
+        // the tag sits between the mac addresses and the arp ethertype
+        assert_eq!(data[12..14], [0x81, 0x00]);
+        assert_eq!(data[16..18], [0x08, 0x06]);
+
+        let (received, len) = Frame::decode(&data).unwrap();
+        assert_eq!(received, sent);
+        assert_eq!(len, data.len());
+    }
+}
+
+#[test]
+fn parses_vlan_ids_and_priorities() {
+    assert_eq!(Vlan::from_str("1").unwrap(), Vlan { id: 1, priority: 0 });
+    assert_eq!(
+        Vlan::from_str(" 4094:7 ").unwrap(),
+        Vlan {
+            id: 4094,
+            priority: 7,
+        }
+    );
+
+    // reserved ids, a priority past 3 bits, and anything that isn't a number
+    for invalid in ["0", "4095", "65536", "10:8", "10:", "-1", "ten", ""] {
+        assert!(
+            Vlan::from_str(invalid).is_err(),
+            "{0:?} was accepted",
+            invalid
+        );
+    }
+}